@@ -0,0 +1,45 @@
+use crate::ast::{Expression, TypeMapping};
+use crate::error::CompileError;
+use crate::tokens::{Location, Token};
+
+/// Puts call arguments in parameter order. Positional arguments fill the
+/// leading parameters and each named one goes to the parameter it names.
+pub fn order_args<T>(
+    params: &[TypeMapping],
+    positional: Vec<T>,
+    named: Vec<(&Token, T)>,
+    location: &Location,
+) -> Result<Vec<T>, CompileError> {
+    let given = positional.len() + named.len();
+    if given != params.len() {
+        return Err(CompileError::type_error(
+            format!("expected {} arguments, but got {}", params.len(), given),
+            location.clone(),
+        ));
+    }
+
+    let mut slots: Vec<Option<T>> = positional.into_iter().map(Some).collect();
+    slots.resize_with(params.len(), || None);
+
+    for (name, value) in named {
+        let Some(i) = params.iter().position(|p| {
+            matches!(&p.expr, Expression::Id { name: param, .. } if param.literal == name.literal)
+        }) else {
+            return Err(CompileError::type_error(
+                format!("unknown parameter '{}'", name.literal),
+                name.location.clone(),
+            ));
+        };
+
+        if slots[i].is_some() {
+            return Err(CompileError::type_error(
+                format!("parameter '{}' is given more than once", name.literal),
+                name.location.clone(),
+            ));
+        }
+        slots[i] = Some(value);
+    }
+
+    // every parameter got exactly one argument, so no slot is left empty
+    Ok(slots.into_iter().flatten().collect())
+}
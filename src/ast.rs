@@ -1,53 +1,195 @@
-use crate::tokens::{Token, TokenKind};
+use crate::tokens::{Location, Span, Token};
+use serde::Serialize;
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Statement {
-    Return { value: Option<Expression> },
-    ExpressionStatement { expression: Expression },
+    Return {
+        value: Option<Expression>,
+        span: Span,
+    },
+    Let {
+        name: Token,
+        type_annotation: Option<Type>,
+        value: Expression,
+        span: Span,
+    },
+    Const {
+        name: Token,
+        type_annotation: Option<Type>,
+        value: Expression,
+        span: Span,
+    },
+    If {
+        condition: Expression,
+        then_branch: Vec<Statement>,
+        else_branch: Option<Vec<Statement>>,
+        span: Span,
+    },
+    While {
+        label: Option<Token>,
+        condition: Expression,
+        body: Vec<Statement>,
+        span: Span,
+    },
+    For {
+        label: Option<Token>,
+        init: Option<Box<Statement>>,
+        condition: Option<Expression>,
+        step: Option<Expression>,
+        body: Vec<Statement>,
+        span: Span,
+    },
+    /// `break;` leaves the innermost loop, `break outer;` the loop labeled
+    /// `outer`.
+    Break {
+        label: Option<Token>,
+        span: Span,
+    },
+    Empty {
+        span: Span,
+    },
+    Continue {
+        label: Option<Token>,
+        span: Span,
+    },
+    StructDecl {
+        name: Token,
+        fields: Vec<TypeMapping>,
+        span: Span,
+    },
+    ExpressionStatement {
+        expression: Expression,
+        span: Span,
+    },
+    /// `import "other.ic";`. Only allowed at the top level; the driver
+    /// replaces it with the statements of the imported file.
+    Import {
+        path: Token,
+        span: Span,
+    },
     Halt,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Expression {
     Binary {
         lhs: Box<Expression>,
-        op: TokenKind,
+        op: Token,
         rhs: Box<Expression>,
+        span: Span,
     },
     Unary {
-        op: TokenKind,
+        op: Token,
         expr: Box<Expression>,
+        span: Span,
+    },
+    Ternary {
+        condition: Box<Expression>,
+        then_expr: Box<Expression>,
+        else_expr: Box<Expression>,
+        span: Span,
+    },
+    Assign {
+        target: Box<Expression>,
+        /// The operator of a compound assignment such as `x += v`.
+        op: Option<Token>,
+        value: Box<Expression>,
+        span: Span,
     },
     FunctionCall {
         callee: Box<Expression>,
         args: Vec<Expression>,
+        named_args: Vec<(Token, Expression)>,
+        span: Span,
     },
     FunctionLiteral {
         name: Option<Token>,
         params: Vec<TypeMapping>,
         return_type: Option<Type>,
         body: Vec<Statement>,
+        span: Span,
+    },
+    Array {
+        elements: Vec<Expression>,
+        span: Span,
+    },
+    /// Parentheses written around an expression, kept so the formatter can
+    /// reproduce them.
+    Grouping {
+        inner: Box<Expression>,
+        span: Span,
+    },
+    Tuple {
+        elements: Vec<Expression>,
+        span: Span,
+    },
+    Index {
+        target: Box<Expression>,
+        index: Box<Expression>,
+        span: Span,
+    },
+    Member {
+        target: Box<Expression>,
+        field: Token,
+        span: Span,
+    },
+    StructLiteral {
+        name: Token,
+        fields: Vec<(Token, Expression)>,
+        span: Span,
     },
     Id {
         name: Token,
+        span: Span,
     },
     Int {
         value: Token,
+        span: Span,
+    },
+    Float {
+        value: Token,
+        span: Span,
+    },
+    Str {
+        value: Token,
+        span: Span,
+    },
+    Char {
+        value: Token,
+        span: Span,
+    },
+    Bool {
+        value: Token,
+        span: Span,
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum Type {
+    Unit,
     Int,
     String,
+    Bool,
+    Array {
+        element: Box<Type>,
+    },
+    Tuple {
+        elements: Vec<Type>,
+    },
+    Optional {
+        inner: Box<Type>,
+    },
+    Named {
+        name: String,
+    },
     Function {
         return_type: Box<Type>,
         param_types: Vec<Type>,
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TypeMapping {
     pub expr: Expression,
     pub t: Type,
@@ -59,19 +201,178 @@ impl TypeMapping {
     }
 }
 
+impl Statement {
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Statement::Return { span, .. }
+            | Statement::Let { span, .. }
+            | Statement::Const { span, .. }
+            | Statement::If { span, .. }
+            | Statement::While { span, .. }
+            | Statement::For { span, .. }
+            | Statement::Break { span, .. }
+            | Statement::Empty { span }
+            | Statement::Continue { span, .. }
+            | Statement::StructDecl { span, .. }
+            | Statement::ExpressionStatement { span, .. }
+            | Statement::Import { span, .. } => Some(span),
+            Statement::Halt => None,
+        }
+    }
+}
+
+impl Expression {
+    pub fn span(&self) -> &Span {
+        match self {
+            Expression::Binary { span, .. }
+            | Expression::Unary { span, .. }
+            | Expression::Ternary { span, .. }
+            | Expression::Assign { span, .. }
+            | Expression::FunctionCall { span, .. }
+            | Expression::FunctionLiteral { span, .. }
+            | Expression::Array { span, .. }
+            | Expression::Grouping { span, .. }
+            | Expression::Tuple { span, .. }
+            | Expression::Index { span, .. }
+            | Expression::Member { span, .. }
+            | Expression::StructLiteral { span, .. }
+            | Expression::Id { span, .. }
+            | Expression::Int { span, .. }
+            | Expression::Float { span, .. }
+            | Expression::Str { span, .. }
+            | Expression::Char { span, .. }
+            | Expression::Bool { span, .. } => span,
+        }
+    }
+
+    pub fn location(&self) -> &Location {
+        &self.span().start
+    }
+
+    /// The expression with any parentheses around it removed, for checks
+    /// that care about what was written rather than how it was grouped.
+    pub fn ungrouped(&self) -> &Expression {
+        match self {
+            Expression::Grouping { inner, .. } => inner.ungrouped(),
+            expr => expr,
+        }
+    }
+
+    /// Whether the expression's source ends in a `}`. As a statement such an
+    /// expression takes no semicolon. Only an uncalled function literal
+    /// qualifies: `fn f() {}` ends in its body, `fn () {}()` in the call.
+    pub fn ends_with_block(&self) -> bool {
+        matches!(self, Expression::FunctionLiteral { .. })
+    }
+}
+
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Statement::ExpressionStatement { expression } => {
-                write!(f, "{};", expression)
+            Statement::ExpressionStatement { expression, .. } => {
+                if expression.ends_with_block() {
+                    write!(f, "{}", expression)
+                } else {
+                    write!(f, "{};", expression)
+                }
             }
-            Statement::Return { value } => {
+            Statement::Return { value, .. } => {
                 if let Some(val) = value {
                     write!(f, "return {}", val)
                 } else {
                     write!(f, "return")
                 }
             }
+            Statement::Let {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => {
+                if let Some(t) = type_annotation {
+                    write!(f, "let {}: {} = {}", name.literal, t, value)
+                } else {
+                    write!(f, "let {} = {}", name.literal, value)
+                }
+            }
+            Statement::Const {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => {
+                if let Some(t) = type_annotation {
+                    write!(f, "const {}: {} = {}", name.literal, t, value)
+                } else {
+                    write!(f, "const {} = {}", name.literal, value)
+                }
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let then_str: Vec<String> = then_branch.iter().map(|s| s.to_string()).collect();
+                write!(f, "if ({}) {{ {} }}", condition, then_str.join(" "))?;
+
+                match else_branch.as_deref() {
+                    Some([nested @ Statement::If { .. }]) => write!(f, " else {}", nested)?,
+                    Some(else_branch) => {
+                        let else_str: Vec<String> =
+                            else_branch.iter().map(|s| s.to_string()).collect();
+                        write!(f, " else {{ {} }}", else_str.join(" "))?;
+                    }
+                    None => {}
+                }
+
+                Ok(())
+            }
+            Statement::While {
+                label,
+                condition,
+                body,
+                ..
+            } => {
+                let body_str: Vec<String> = body.iter().map(|s| s.to_string()).collect();
+                write!(
+                    f,
+                    "{}while ({}) {{ {} }}",
+                    label_prefix(label.as_ref()),
+                    condition,
+                    body_str.join(" ")
+                )
+            }
+            Statement::For {
+                label,
+                init,
+                condition,
+                step,
+                body,
+                ..
+            } => {
+                let clause = |c: Option<String>| c.unwrap_or_default();
+                let body_str: Vec<String> = body.iter().map(|s| s.to_string()).collect();
+                write!(
+                    f,
+                    "{}for ({}; {}; {}) {{ {} }}",
+                    label_prefix(label.as_ref()),
+                    clause(init.as_ref().map(|s| s.to_string())),
+                    clause(condition.as_ref().map(|c| c.to_string())),
+                    clause(step.as_ref().map(|s| s.to_string())),
+                    body_str.join(" ")
+                )
+            }
+            Statement::Break { label, .. } => write!(f, "{}", jump_string("break", label.as_ref())),
+            Statement::Empty { .. } => write!(f, ";"),
+            Statement::Continue { label, .. } => {
+                write!(f, "{}", jump_string("continue", label.as_ref()))
+            }
+            Statement::StructDecl { name, fields, .. } => {
+                let fields_str: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+                write!(f, "struct {} {{ {} }}", name.literal, fields_str.join(", "))
+            }
+            Statement::Import { path, .. } => write!(f, "import {};", path.literal),
             Statement::Halt => write!(f, "EOF"),
         }
     }
@@ -80,20 +381,81 @@ impl fmt::Display for Statement {
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expression::Binary { lhs, op, rhs } => {
-                write!(f, "({} {} {})", lhs, op, rhs)
+            Expression::Binary { lhs, op, rhs, .. } => {
+                write!(f, "{} {} {}", lhs, op.kind, rhs)
             }
-            Expression::Unary { op, expr } => {
-                write!(f, "({}{})", op, expr)
+            Expression::Unary { op, expr, .. } => {
+                write!(f, "{}{}", op.kind, expr)
             }
-            Expression::FunctionCall { callee, args } => {
-                let args_str: Vec<String> = args.iter().map(|a| a.to_string()).collect();
-                write!(f, "(fcall: {}({}))", callee, args_str.join(", "))
+            Expression::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                write!(f, "{} ? {} : {}", condition, then_expr, else_expr)
             }
-            Expression::Id { name } => {
+            Expression::Assign {
+                target, op, value, ..
+            } => match op {
+                Some(op) => write!(f, "{} {}= {}", target, op.kind, value),
+                None => write!(f, "{} = {}", target, value),
+            },
+            Expression::FunctionCall {
+                callee,
+                args,
+                named_args,
+                ..
+            } => {
+                let args_str: Vec<String> = args
+                    .iter()
+                    .map(|a| a.to_string())
+                    .chain(
+                        named_args
+                            .iter()
+                            .map(|(name, a)| format!("{}: {}", name.literal, a)),
+                    )
+                    .collect();
+                write!(f, "{}({})", callee, args_str.join(", "))
+            }
+            Expression::Array { elements, .. } => {
+                let elements_str: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "[{}]", elements_str.join(", "))
+            }
+            Expression::Grouping { inner, .. } => write!(f, "({})", inner),
+            Expression::Tuple { elements, .. } => {
+                let elements_str: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", tuple_string(&elements_str))
+            }
+            Expression::Index { target, index, .. } => {
+                write!(f, "{}[{}]", target, index)
+            }
+            Expression::Member { target, field, .. } => {
+                write!(f, "{}.{}", target, field.literal)
+            }
+            Expression::StructLiteral { name, fields, .. } => {
+                let fields_str: Vec<String> = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field.literal, value))
+                    .collect();
+                write!(f, "{} {{ {} }}", name.literal, fields_str.join(", "))
+            }
+            Expression::Id { name, .. } => {
                 write!(f, "{}", name.literal)
             }
-            Expression::Int { value } => {
+            Expression::Int { value, .. } => {
+                write!(f, "{}", value.literal)
+            }
+            Expression::Float { value, .. } => {
+                write!(f, "{}", value.literal)
+            }
+            Expression::Str { value, .. } => {
+                write!(f, "{}", value.literal)
+            }
+            Expression::Char { value, .. } => {
+                write!(f, "{}", value.literal)
+            }
+            Expression::Bool { value, .. } => {
                 write!(f, "{}", value.literal)
             }
             Expression::FunctionLiteral {
@@ -101,10 +463,11 @@ impl fmt::Display for Expression {
                 params,
                 return_type,
                 body,
+                ..
             } => {
                 let name_str = name
                     .as_ref()
-                    .map(|t| t.literal.clone())
+                    .map(|t| t.literal.to_string())
                     .unwrap_or("<anon>".to_string());
 
                 let params_str: Vec<String> = params
@@ -136,11 +499,25 @@ impl fmt::Display for Expression {
 impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Type::Unit => write!(f, "void"),
             Type::Int => write!(f, "int"),
             Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Array { element } => write!(f, "[{}]", element),
+            Type::Tuple { elements } => {
+                let elements_str: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", tuple_string(&elements_str))
+            }
+            // `fn() -> int?` would read as a function returning an optional
+            Type::Optional { inner } if matches!(**inner, Type::Function { .. }) => {
+                write!(f, "({})?", inner)
+            }
+            Type::Optional { inner } => write!(f, "{}?", inner),
+            Type::Named { name } => write!(f, "{}", name),
             Type::Function {
                 return_type,
                 param_types,
+                ..
             } => {
                 let params_str: Vec<String> = param_types.iter().map(|p| p.to_string()).collect();
                 write!(f, "fn({}) -> {}", params_str.join(", "), return_type)
@@ -149,6 +526,30 @@ impl fmt::Display for Type {
     }
 }
 
+/// `(a, b)`, with the trailing comma that tells a one-tuple `(a,)` apart
+/// from a parenthesized `(a)`.
+pub fn tuple_string(elements: &[String]) -> String {
+    match elements {
+        [only] => format!("({},)", only),
+        _ => format!("({})", elements.join(", ")),
+    }
+}
+
+/// `outer: ` in front of a labeled loop, nothing for an unlabeled one.
+pub fn label_prefix(label: Option<&Token>) -> String {
+    label
+        .map(|label| format!("{}: ", label.literal))
+        .unwrap_or_default()
+}
+
+/// `break;` or `break outer;`, likewise for `continue`.
+pub fn jump_string(keyword: &str, label: Option<&Token>) -> String {
+    match label {
+        Some(label) => format!("{} {};", keyword, label.literal),
+        None => format!("{};", keyword),
+    }
+}
+
 impl fmt::Display for TypeMapping {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}: {}", self.expr, self.t)
@@ -0,0 +1,286 @@
+use crate::ast::{Expression, Statement, Type, TypeMapping};
+use crate::tokens::{Span, Token, TokenKind};
+use crate::visitors::visitor::EVisitor;
+
+/// A single step of the stack machine. Operators pop their operands and push
+/// the result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    PushInt(i64),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    Return,
+}
+
+/// Lowers an integer expression into instructions ending in `Return`.
+pub fn compile_expr(expr: &Expression) -> Result<Vec<Instruction>, String> {
+    let mut compiler = Compiler { code: Vec::new() };
+    EVisitor::visit(&mut compiler, expr)?;
+    compiler.code.push(Instruction::Return);
+
+    Ok(compiler.code)
+}
+
+struct Compiler {
+    code: Vec<Instruction>,
+}
+
+impl Compiler {
+    fn unsupported(what: &str) -> Result<(), String> {
+        Err(format!("{} is not supported by the bytecode compiler", what))
+    }
+}
+
+impl EVisitor<()> for Compiler {
+    fn visit_binary(&mut self, lhs: &Expression, op: &Token, rhs: &Expression) -> Result<(), String> {
+        let instruction = match op.kind {
+            TokenKind::Plus => Instruction::Add,
+            TokenKind::Minus => Instruction::Sub,
+            TokenKind::Star => Instruction::Mul,
+            TokenKind::Slash => Instruction::Div,
+            _ => {
+                return Err(format!(
+                    "operator '{}' is not supported by the bytecode compiler at {}",
+                    op.kind, op.location
+                ));
+            }
+        };
+
+        EVisitor::visit(self, lhs)?;
+        EVisitor::visit(self, rhs)?;
+        self.code.push(instruction);
+
+        Ok(())
+    }
+
+    fn visit_unary(&mut self, op: &Token, expr: &Expression) -> Result<(), String> {
+        EVisitor::visit(self, expr)?;
+
+        match op.kind {
+            TokenKind::Plus => Ok(()),
+            TokenKind::Minus => {
+                self.code.push(Instruction::Neg);
+                Ok(())
+            }
+            _ => Err(format!(
+                "operator '{}' is not supported by the bytecode compiler at {}",
+                op.kind, op.location
+            )),
+        }
+    }
+
+    fn visit_ternary(
+        &mut self,
+        _condition: &Expression,
+        _then_expr: &Expression,
+        _else_expr: &Expression,
+    ) -> Result<(), String> {
+        Self::unsupported("ternary")
+    }
+
+    fn visit_assign(
+        &mut self,
+        _target: &Expression,
+        _op: Option<&Token>,
+        _value: &Expression,
+    ) -> Result<(), String> {
+        Self::unsupported("assignment")
+    }
+
+    fn visit_function_call(
+        &mut self,
+        _callee: &Expression,
+        _args: &[Expression],
+        _named_args: &[(Token, Expression)],
+    ) -> Result<(), String> {
+        Self::unsupported("function call")
+    }
+
+    fn visit_function_literal(
+        &mut self,
+        _name: &Option<Token>,
+        _params: &[TypeMapping],
+        _return_type: &Option<Type>,
+        _body: &[Statement],
+    ) -> Result<(), String> {
+        Self::unsupported("function literal")
+    }
+
+    fn visit_array(&mut self, _elements: &[Expression], _span: &Span) -> Result<(), String> {
+        Self::unsupported("array literal")
+    }
+
+    fn visit_grouping(&mut self, inner: &Expression) -> Result<(), String> {
+        EVisitor::visit(self, inner)
+    }
+
+    fn visit_tuple(&mut self, _elements: &[Expression]) -> Result<(), String> {
+        Self::unsupported("tuple")
+    }
+
+    fn visit_index(&mut self, _target: &Expression, _index: &Expression) -> Result<(), String> {
+        Self::unsupported("indexing")
+    }
+
+    fn visit_member(&mut self, _target: &Expression, _field: &Token) -> Result<(), String> {
+        Self::unsupported("member access")
+    }
+
+    fn visit_struct_literal(
+        &mut self,
+        _name: &Token,
+        _fields: &[(Token, Expression)],
+    ) -> Result<(), String> {
+        Self::unsupported("struct literal")
+    }
+
+    fn visit_id(&mut self, _name: Token) -> Result<(), String> {
+        Self::unsupported("identifier")
+    }
+
+    fn visit_int(&mut self, value: Token) -> Result<(), String> {
+        self.code.push(Instruction::PushInt(value.int_value()?));
+        Ok(())
+    }
+
+    fn visit_float(&mut self, _value: Token) -> Result<(), String> {
+        Self::unsupported("float literal")
+    }
+
+    fn visit_str(&mut self, _value: Token) -> Result<(), String> {
+        Self::unsupported("string literal")
+    }
+
+    fn visit_char(&mut self, _value: Token) -> Result<(), String> {
+        Self::unsupported("character literal")
+    }
+
+    fn visit_bool(&mut self, _value: Token) -> Result<(), String> {
+        Self::unsupported("boolean literal")
+    }
+}
+
+/// Stack machine for code produced by `compile_expr`. Arithmetic is checked
+/// the same way the tree-walking interpreter checks it.
+#[derive(Default)]
+pub struct VM {
+    stack: Vec<i64>,
+}
+
+impl VM {
+    pub fn new() -> Self {
+        Self { stack: Vec::new() }
+    }
+
+    pub fn run(&mut self, code: &[Instruction]) -> Result<i64, String> {
+        self.stack.clear();
+
+        for instruction in code {
+            match instruction {
+                Instruction::PushInt(v) => self.stack.push(*v),
+                Instruction::Add => self.binary(i64::checked_add, "addition")?,
+                Instruction::Sub => self.binary(i64::checked_sub, "subtraction")?,
+                Instruction::Mul => self.binary(i64::checked_mul, "multiplication")?,
+                Instruction::Div => {
+                    if self.stack.last() == Some(&0) {
+                        return Err("division by zero".into());
+                    }
+
+                    self.binary(i64::checked_div, "division")?
+                }
+                Instruction::Neg => {
+                    let v = self.pop()?;
+                    let negated = v
+                        .checked_neg()
+                        .ok_or_else(|| "integer overflow in negation".to_string())?;
+                    self.stack.push(negated);
+                }
+                Instruction::Return => return self.pop(),
+            }
+        }
+
+        Err("bytecode ended without a return".into())
+    }
+
+    fn binary(&mut self, op: fn(i64, i64) -> Option<i64>, what: &str) -> Result<(), String> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        let result = op(lhs, rhs).ok_or_else(|| format!("integer overflow in {}", what))?;
+        self.stack.push(result);
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i64, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "stack underflow in bytecode".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, Value};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn expr(src: &str) -> Expression {
+        let tokens = Lexer::from_str(src).lex().expect("source should lex");
+        let stmts = Parser::new(tokens).parse().expect("source should parse");
+        match stmts.into_iter().next() {
+            Some(Statement::ExpressionStatement { expression, .. }) => expression,
+            other => panic!("expected an expression statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn vm_agrees_with_the_interpreter() {
+        for src in [
+            "2 + 3 * 4;",
+            "-(1 - 8) / 2;",
+            "(10 - 4) * -3;",
+            "7 / 2 - 1;",
+        ] {
+            let expr = expr(src);
+            let code = compile_expr(&expr).unwrap();
+            let evaluated = Interpreter::new().eval(&expr).unwrap();
+
+            assert_eq!(
+                Value::Int(VM::new().run(&code).unwrap()),
+                evaluated,
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn compiles_to_stack_instructions() {
+        assert_eq!(
+            compile_expr(&expr("1 + 2 * -3;")),
+            Ok(vec![
+                Instruction::PushInt(1),
+                Instruction::PushInt(2),
+                Instruction::PushInt(3),
+                Instruction::Neg,
+                Instruction::Mul,
+                Instruction::Add,
+                Instruction::Return,
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_runtime_and_unsupported_errors() {
+        let code = compile_expr(&expr("1 / 0;")).unwrap();
+        assert_eq!(VM::new().run(&code), Err("division by zero".to_string()));
+        assert_eq!(
+            compile_expr(&expr("x + 1;")),
+            Err("identifier is not supported by the bytecode compiler".to_string())
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+/// Nested lexical scopes. The outermost scope holds the globals and is never
+/// popped; names resolve from the innermost scope outward.
+pub struct Env<T> {
+    scopes: Vec<HashMap<String, T>>,
+}
+
+impl<T> Env<T> {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: T) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), value);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Looks `name` up in the innermost scope only.
+    pub fn get_local(&self, name: &str) -> Option<&T> {
+        self.scopes.last().and_then(|scope| scope.get(name))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    /// Removes every scope above the globals, so a function body can run
+    /// without seeing its caller's locals. Hand the result back to
+    /// `restore_locals` once the call is done.
+    pub fn take_locals(&mut self) -> Vec<HashMap<String, T>> {
+        self.scopes.split_off(1)
+    }
+
+    /// A copy of every scope above the globals, for a closure to capture.
+    pub fn locals(&self) -> Vec<HashMap<String, T>>
+    where
+        T: Clone,
+    {
+        self.scopes[1..].to_vec()
+    }
+
+    pub fn restore_locals(&mut self, locals: Vec<HashMap<String, T>>) {
+        self.scopes.truncate(1);
+        self.scopes.extend(locals);
+    }
+}
+
+impl<T> Default for Env<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inner_scopes_shadow_outer_ones() {
+        let mut env = Env::new();
+        env.define("x", 1);
+        env.push_scope();
+        env.define("x", 2);
+        assert_eq!(env.get("x"), Some(&2));
+
+        env.pop_scope();
+        assert_eq!(env.get("x"), Some(&1));
+    }
+
+    #[test]
+    fn looks_names_up_through_enclosing_scopes() {
+        let mut env = Env::new();
+        env.define("x", 1);
+        env.push_scope();
+        env.push_scope();
+
+        assert_eq!(env.get_local("x"), None);
+        assert_eq!(env.get("x"), Some(&1));
+    }
+
+    #[test]
+    fn undefined_names_are_not_found() {
+        let mut env: Env<i32> = Env::new();
+        env.push_scope();
+        env.define("x", 1);
+        env.pop_scope();
+
+        assert_eq!(env.get("x"), None);
+        assert_eq!(env.get("y"), None);
+    }
+
+    #[test]
+    fn globals_are_never_popped() {
+        let mut env = Env::new();
+        env.define("x", 1);
+        env.pop_scope();
+        assert_eq!(env.get("x"), Some(&1));
+    }
+}
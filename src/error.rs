@@ -0,0 +1,97 @@
+use std::fmt;
+
+use crate::tokens::Location;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    Lexical,
+    Syntax,
+    Type,
+    Import,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompileError {
+    pub message: String,
+    pub location: Location,
+    pub kind: ErrorKind,
+}
+
+impl CompileError {
+    pub fn new(kind: ErrorKind, message: String, location: Location) -> Self {
+        Self {
+            message,
+            location,
+            kind,
+        }
+    }
+
+    pub fn lexical(message: String, location: Location) -> Self {
+        Self::new(ErrorKind::Lexical, message, location)
+    }
+
+    pub fn syntax(message: String, location: Location) -> Self {
+        Self::new(ErrorKind::Syntax, message, location)
+    }
+
+    pub fn type_error(message: String, location: Location) -> Self {
+        Self::new(ErrorKind::Type, message, location)
+    }
+
+    pub fn import(message: String, location: Location) -> Self {
+        Self::new(ErrorKind::Import, message, location)
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.location)
+    }
+}
+
+pub fn render_error(src: &str, location: &Location, message: &str) -> String {
+    let line = src
+        .lines()
+        .nth(location.line.saturating_sub(1))
+        .unwrap_or("");
+    let line_no = location.line.to_string();
+    let gutter = " ".repeat(line_no.len());
+    let padding: String = line
+        .chars()
+        .take(location.col.saturating_sub(1))
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    format!(
+        "{}\n{} |\n{} | {}\n{} | {}^",
+        message, gutter, line_no, line, gutter, padding
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_a_caret_under_the_column() {
+        let src = "let a = 1;\nlet b = $;";
+        let rendered = render_error(src, &Location::new(2, 9), "unrecognized character");
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(
+            lines,
+            [
+                "unrecognized character",
+                "  |",
+                "2 | let b = $;",
+                "  |         ^",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_tabs_in_the_caret_padding() {
+        let rendered = render_error("\tx $", &Location::new(1, 4), "bad");
+        assert!(rendered.ends_with("1 | \tx $\n  | \t  ^"));
+    }
+}
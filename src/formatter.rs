@@ -0,0 +1,498 @@
+use crate::ast::{Expression, Statement, Type, TypeMapping, jump_string, label_prefix, tuple_string};
+use crate::parser::Parser;
+use crate::tokens::{Span, Token, TokenKind};
+use crate::visitors::visitor::{EVisitor, SVisitor};
+
+const INDENT: &str = "    ";
+
+pub fn format_program(stmts: &[Statement]) -> Result<String, String> {
+    let mut formatter = Formatter::new();
+    let mut out = String::new();
+
+    for stmt in stmts {
+        let line = formatter.format_stmt(stmt)?;
+        if !line.is_empty() {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+
+    Ok(out)
+}
+
+#[derive(Default)]
+pub struct Formatter {
+    depth: usize,
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Self { depth: 0 }
+    }
+
+    pub fn format_stmt(&mut self, stmt: &Statement) -> Result<String, String> {
+        SVisitor::visit(self, stmt)
+    }
+
+    pub fn format_expr(&mut self, expr: &Expression) -> Result<String, String> {
+        EVisitor::visit(self, expr)
+    }
+
+    fn format_block(&mut self, stmts: &[Statement]) -> Result<String, String> {
+        if stmts.is_empty() {
+            return Ok("{}".into());
+        }
+
+        self.depth += 1;
+        let lines = stmts
+            .iter()
+            .map(|stmt| {
+                self.format_stmt(stmt)
+                    .map(|line| format!("{}{}", INDENT.repeat(self.depth), line))
+            })
+            .collect::<Result<Vec<String>, String>>();
+        self.depth -= 1;
+
+        Ok(format!(
+            "{{\n{}\n{}}}",
+            lines?.join("\n"),
+            INDENT.repeat(self.depth)
+        ))
+    }
+
+    fn format_operand(&mut self, expr: &Expression, needs_parens: bool) -> Result<String, String> {
+        let s = self.format_expr(expr)?;
+
+        if needs_parens {
+            return Ok(format!("({})", s));
+        }
+
+        Ok(s)
+    }
+
+    fn is_postfix_operand(expr: &Expression) -> bool {
+        !matches!(
+            expr,
+            Expression::Binary { .. }
+                | Expression::Unary { .. }
+                | Expression::Ternary { .. }
+                | Expression::Assign { .. }
+        )
+    }
+
+    fn symbol(op: &TokenKind) -> Result<&'static str, String> {
+        op.symbol()
+            .ok_or_else(|| format!("'{}' is not an operator", op))
+    }
+}
+
+impl SVisitor<String> for Formatter {
+    fn visit_return(&mut self, value: Option<&Expression>, _span: &Span) -> Result<String, String> {
+        match value {
+            Some(value) => Ok(format!("return {};", self.format_expr(value)?)),
+            None => Ok("return;".into()),
+        }
+    }
+
+    fn visit_let(
+        &mut self,
+        name: &Token,
+        type_annotation: Option<&Type>,
+        value: &Expression,
+    ) -> Result<String, String> {
+        let value = self.format_expr(value)?;
+
+        match type_annotation {
+            Some(t) => Ok(format!("let {}: {} = {};", name.literal, t, value)),
+            None => Ok(format!("let {} = {};", name.literal, value)),
+        }
+    }
+
+    fn visit_const(
+        &mut self,
+        name: &Token,
+        type_annotation: Option<&Type>,
+        value: &Expression,
+    ) -> Result<String, String> {
+        let value = self.format_expr(value)?;
+
+        match type_annotation {
+            Some(t) => Ok(format!("const {}: {} = {};", name.literal, t, value)),
+            None => Ok(format!("const {} = {};", name.literal, value)),
+        }
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expression,
+        then_branch: &[Statement],
+        else_branch: Option<&[Statement]>,
+    ) -> Result<String, String> {
+        let mut out = format!(
+            "if ({}) {}",
+            self.format_expr(condition)?,
+            self.format_block(then_branch)?
+        );
+
+        match else_branch {
+            Some([nested @ Statement::If { .. }]) => {
+                out.push_str(&format!(" else {}", self.format_stmt(nested)?));
+            }
+            Some(else_branch) => {
+                out.push_str(&format!(" else {}", self.format_block(else_branch)?));
+            }
+            None => {}
+        }
+
+        Ok(out)
+    }
+
+    fn visit_while(
+        &mut self,
+        label: Option<&Token>,
+        condition: &Expression,
+        body: &[Statement],
+    ) -> Result<String, String> {
+        Ok(format!(
+            "{}while ({}) {}",
+            label_prefix(label),
+            self.format_expr(condition)?,
+            self.format_block(body)?
+        ))
+    }
+
+    fn visit_for(
+        &mut self,
+        label: Option<&Token>,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) -> Result<String, String> {
+        let init = match init {
+            Some(init) => self.format_stmt(init)?,
+            None => ";".into(),
+        };
+        let condition = match condition {
+            Some(condition) => format!(" {};", self.format_expr(condition)?),
+            None => ";".into(),
+        };
+        let step = match step {
+            Some(step) => format!(" {}", self.format_expr(step)?),
+            None => String::new(),
+        };
+
+        Ok(format!(
+            "{}for ({}{}{}) {}",
+            label_prefix(label),
+            init,
+            condition,
+            step,
+            self.format_block(body)?
+        ))
+    }
+
+    fn visit_break(&mut self, label: Option<&Token>, _span: &Span) -> Result<String, String> {
+        Ok(jump_string("break", label))
+    }
+
+    fn visit_continue(&mut self, label: Option<&Token>, _span: &Span) -> Result<String, String> {
+        Ok(jump_string("continue", label))
+    }
+
+    fn visit_struct_decl(&mut self, name: &Token, fields: &[TypeMapping]) -> Result<String, String> {
+        if fields.is_empty() {
+            return Ok(format!("struct {} {{}}", name.literal));
+        }
+
+        let pad = INDENT.repeat(self.depth + 1);
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|field| format!("{}{},", pad, field))
+            .collect();
+
+        Ok(format!(
+            "struct {} {{\n{}\n{}}}",
+            name.literal,
+            fields.join("\n"),
+            INDENT.repeat(self.depth)
+        ))
+    }
+
+    fn visit_expression_stmt(&mut self, expr: &Expression) -> Result<String, String> {
+        let s = self.format_expr(expr)?;
+
+        if expr.ends_with_block() {
+            Ok(s)
+        } else {
+            Ok(format!("{};", s))
+        }
+    }
+
+    fn visit_import(&mut self, path: &Token) -> Result<String, String> {
+        Ok(format!("import {};", path.literal))
+    }
+
+    fn visit_empty(&mut self) -> Result<String, String> {
+        Ok(";".into())
+    }
+
+    fn visit_halt(&mut self) -> Result<String, String> {
+        Ok(String::new())
+    }
+}
+
+impl EVisitor<String> for Formatter {
+    fn visit_binary(
+        &mut self,
+        lhs: &Expression,
+        op: &Token,
+        rhs: &Expression,
+    ) -> Result<String, String> {
+        let symbol = Self::symbol(&op.kind)?;
+        let (l_bp, r_bp) = Parser::get_binding_power(op.kind);
+
+        // a child only keeps its parentheses when the parser would otherwise
+        // attach it to a different operator
+        let lhs_parens = match lhs {
+            Expression::Binary { op: inner, .. } => l_bp >= Parser::get_binding_power(inner.kind).1,
+            Expression::Ternary { .. } | Expression::Assign { .. } => true,
+            _ => false,
+        };
+        let rhs_parens = match rhs {
+            Expression::Binary { op: inner, .. } => Parser::get_binding_power(inner.kind).0 < r_bp,
+            Expression::Ternary { .. } | Expression::Assign { .. } => true,
+            _ => false,
+        };
+
+        Ok(format!(
+            "{} {} {}",
+            self.format_operand(lhs, lhs_parens)?,
+            symbol,
+            self.format_operand(rhs, rhs_parens)?
+        ))
+    }
+
+    fn visit_unary(&mut self, op: &Token, expr: &Expression) -> Result<String, String> {
+        let symbol = Self::symbol(&op.kind)?;
+        let needs_parens = !Self::is_postfix_operand(expr);
+        Ok(format!("{}{}", symbol, self.format_operand(expr, needs_parens)?))
+    }
+
+    fn visit_ternary(
+        &mut self,
+        condition: &Expression,
+        then_expr: &Expression,
+        else_expr: &Expression,
+    ) -> Result<String, String> {
+        let condition_parens = matches!(
+            condition,
+            Expression::Ternary { .. } | Expression::Assign { .. }
+        );
+        let else_parens = matches!(else_expr, Expression::Assign { .. });
+
+        Ok(format!(
+            "{} ? {} : {}",
+            self.format_operand(condition, condition_parens)?,
+            self.format_expr(then_expr)?,
+            self.format_operand(else_expr, else_parens)?
+        ))
+    }
+
+    fn visit_assign(
+        &mut self,
+        target: &Expression,
+        op: Option<&Token>,
+        value: &Expression,
+    ) -> Result<String, String> {
+        let op = match op {
+            Some(op) => format!("{}=", Self::symbol(&op.kind)?),
+            None => "=".into(),
+        };
+
+        Ok(format!(
+            "{} {} {}",
+            self.format_expr(target)?,
+            op,
+            self.format_expr(value)?
+        ))
+    }
+
+    fn visit_function_call(
+        &mut self,
+        callee: &Expression,
+        args: &[Expression],
+        named_args: &[(Token, Expression)],
+    ) -> Result<String, String> {
+        let mut args = args
+            .iter()
+            .map(|arg| self.format_expr(arg))
+            .collect::<Result<Vec<String>, String>>()?;
+        for (name, arg) in named_args {
+            args.push(format!("{}: {}", name.literal, self.format_expr(arg)?));
+        }
+
+        let needs_parens = !Self::is_postfix_operand(callee);
+        Ok(format!(
+            "{}({})",
+            self.format_operand(callee, needs_parens)?,
+            args.join(", ")
+        ))
+    }
+
+    fn visit_function_literal(
+        &mut self,
+        name: &Option<Token>,
+        params: &[TypeMapping],
+        return_type: &Option<Type>,
+        body: &[Statement],
+    ) -> Result<String, String> {
+        let mut out = String::from("fn");
+
+        if let Some(name) = name {
+            out.push(' ');
+            out.push_str(&name.literal);
+        }
+
+        let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+        out.push_str(&format!("({})", params.join(", ")));
+
+        if let Some(return_type) = return_type {
+            out.push_str(&format!(" -> {}", return_type));
+        }
+
+        out.push_str(&format!(" {}", self.format_block(body)?));
+        Ok(out)
+    }
+
+    fn visit_array(&mut self, elements: &[Expression], _span: &Span) -> Result<String, String> {
+        let elements = elements
+            .iter()
+            .map(|e| self.format_expr(e))
+            .collect::<Result<Vec<String>, String>>()?;
+
+        Ok(format!("[{}]", elements.join(", ")))
+    }
+
+    fn visit_grouping(&mut self, inner: &Expression) -> Result<String, String> {
+        Ok(format!("({})", self.format_expr(inner)?))
+    }
+
+    fn visit_tuple(&mut self, elements: &[Expression]) -> Result<String, String> {
+        let elements = elements
+            .iter()
+            .map(|e| self.format_expr(e))
+            .collect::<Result<Vec<String>, String>>()?;
+
+        Ok(tuple_string(&elements))
+    }
+
+    fn visit_index(&mut self, target: &Expression, index: &Expression) -> Result<String, String> {
+        let needs_parens = !Self::is_postfix_operand(target);
+        Ok(format!(
+            "{}[{}]",
+            self.format_operand(target, needs_parens)?,
+            self.format_expr(index)?
+        ))
+    }
+
+    fn visit_member(&mut self, target: &Expression, field: &Token) -> Result<String, String> {
+        let needs_parens = !Self::is_postfix_operand(target);
+        Ok(format!(
+            "{}.{}",
+            self.format_operand(target, needs_parens)?,
+            field.literal
+        ))
+    }
+
+    fn visit_struct_literal(
+        &mut self,
+        name: &Token,
+        fields: &[(Token, Expression)],
+    ) -> Result<String, String> {
+        if fields.is_empty() {
+            return Ok(format!("{} {{}}", name.literal));
+        }
+
+        let fields = fields
+            .iter()
+            .map(|(field, value)| Ok(format!("{}: {}", field.literal, self.format_expr(value)?)))
+            .collect::<Result<Vec<String>, String>>()?;
+
+        Ok(format!("{} {{ {} }}", name.literal, fields.join(", ")))
+    }
+
+    fn visit_id(&mut self, name: Token) -> Result<String, String> {
+        Ok(name.literal.to_string())
+    }
+
+    fn visit_int(&mut self, value: Token) -> Result<String, String> {
+        Ok(value.literal.to_string())
+    }
+
+    fn visit_float(&mut self, value: Token) -> Result<String, String> {
+        Ok(value.literal.to_string())
+    }
+
+    fn visit_str(&mut self, value: Token) -> Result<String, String> {
+        Ok(value.literal.to_string())
+    }
+
+    fn visit_char(&mut self, value: Token) -> Result<String, String> {
+        Ok(value.literal.to_string())
+    }
+
+    fn visit_bool(&mut self, value: Token) -> Result<String, String> {
+        Ok(value.literal.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn format(src: &str) -> String {
+        let tokens = Lexer::from_str(src).lex().expect("source should lex");
+        let stmts = Parser::new(tokens).parse().expect("source should parse");
+        format_program(&stmts).expect("program should format")
+    }
+
+    #[test]
+    fn formats_nested_functions_stably() {
+        let formatted = format(
+            "fn outer(a: int) -> int { let inner = fn (b: int) -> int { return (a + b) * 2; }; if (a > 1) { return inner(a - 1); } return 0; }",
+        );
+
+        assert_eq!(
+            formatted,
+            concat!(
+                "fn outer(a: int) -> int {\n",
+                "    let inner = fn(b: int) -> int {\n",
+                "        return (a + b) * 2;\n",
+                "    };\n",
+                "    if (a > 1) {\n",
+                "        return inner(a - 1);\n",
+                "    }\n",
+                "    return 0;\n",
+                "}\n",
+            )
+        );
+        assert_eq!(format(&formatted), formatted);
+    }
+
+    #[test]
+    fn prints_operators_by_their_symbol() {
+        assert_eq!(
+            format("a = !b && c <= d % 2 ^ e;"),
+            "a = !b && c <= d % 2 ^ e;\n"
+        );
+    }
+
+    #[test]
+    fn keeps_explicit_grouping() {
+        assert_eq!(format("(1 + 2) * 3;"), "(1 + 2) * 3;\n");
+        assert_eq!(format("x = (a * b) + (c);"), "x = (a * b) + (c);\n");
+        assert_eq!(format("1 + 2 * 3;"), "1 + 2 * 3;\n");
+    }
+}
@@ -0,0 +1,1170 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use crate::args::order_args;
+use crate::ast::{Expression, Statement, Type, TypeMapping};
+use crate::env::Env;
+use crate::tokens::{Location, Span, Token, TokenKind};
+use crate::visitors::visitor::{EVisitor, SVisitor};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+    Function(Rc<Function>),
+    Unit,
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{}", v),
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::String(v) => write!(f, "{}", v),
+            Value::Array(elements) => {
+                let elements_str: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "[{}]", elements_str.join(", "))
+            }
+            Value::Function(function) => match &function.name {
+                Some(name) => write!(f, "fn {}", name.literal),
+                None => write!(f, "fn <anon>"),
+            },
+            Value::Unit => write!(f, "void"),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Int(l), Value::Int(r)) => l == r,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Array(l), Value::Array(r)) => l == r,
+            (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+            (Value::Unit, Value::Unit) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    /// The name of the value's type, as the type checker spells it.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Function(_) => "fn",
+            Value::Unit => "void",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Function {
+    name: Option<Token>,
+    params: Vec<TypeMapping>,
+    body: Vec<Statement>,
+    /// The locals in scope where the function was defined, copied at that
+    /// point, so the body sees the same names the typechecker resolved.
+    captured: Vec<HashMap<String, Value>>,
+}
+
+/// What a statement tells the enclosing block to do next. A loop jump
+/// carries the label it targets, `None` meaning the innermost loop.
+pub enum Flow {
+    Next,
+    Break(Option<Rc<str>>),
+    Continue(Option<Rc<str>>),
+    Return(Value),
+}
+
+/// Limit on how deeply calls may nest before the interpreter gives up, well
+/// before the recursion could overflow the stack.
+const MAX_CALL_DEPTH: usize = 512;
+
+/// An assignment target whose indexes have already been evaluated, listed
+/// from the variable outwards.
+struct Place {
+    name: Token,
+    indexes: Vec<(Value, Location)>,
+}
+
+pub struct Interpreter {
+    env: Env<Value>,
+    /// How many calls the interpreter is currently inside.
+    depth: usize,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            env: Env::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn run(&mut self, stmts: &[Statement]) -> Result<(), String> {
+        for stmt in stmts {
+            match self.exec(stmt)? {
+                Flow::Next => {}
+                Flow::Return(_) => break,
+                Flow::Break(_) | Flow::Continue(_) => return Err(Self::stray_loop_jump()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a single top-level statement. Expression statements hand back
+    /// the value they produced so callers can show it.
+    pub fn run_stmt(&mut self, stmt: &Statement) -> Result<Option<Value>, String> {
+        if let Statement::ExpressionStatement { expression, .. } = stmt {
+            return self.eval(expression).map(Some);
+        }
+
+        self.exec(stmt).map(|_| None)
+    }
+
+    pub fn eval(&mut self, expr: &Expression) -> Result<Value, String> {
+        EVisitor::visit(self, expr)
+    }
+
+    fn exec(&mut self, stmt: &Statement) -> Result<Flow, String> {
+        SVisitor::visit(self, stmt)
+    }
+
+    fn exec_block(&mut self, stmts: &[Statement]) -> Result<Flow, String> {
+        self.env.push_scope();
+        let result = self.exec_stmts(stmts);
+        self.env.pop_scope();
+
+        result
+    }
+
+    fn exec_stmts(&mut self, stmts: &[Statement]) -> Result<Flow, String> {
+        for stmt in stmts {
+            match self.exec(stmt)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn eval_condition(&mut self, condition: &Expression, what: &str) -> Result<bool, String> {
+        match self.eval(condition)? {
+            Value::Bool(v) => Ok(v),
+            other => Err(format!(
+                "{} must be a bool, found {} at {}",
+                what,
+                other.type_name(),
+                condition.location()
+            )),
+        }
+    }
+
+    fn call(
+        &mut self,
+        callee: &Expression,
+        function: Rc<Function>,
+        args: Vec<Value>,
+    ) -> Result<Value, String> {
+        if function.params.len() != args.len() {
+            return Err(format!(
+                "expected {} arguments, but got {} at {}",
+                function.params.len(),
+                args.len(),
+                callee.location()
+            ));
+        }
+
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(format!(
+                "maximum call depth of {} exceeded at {}",
+                MAX_CALL_DEPTH,
+                callee.location()
+            ));
+        }
+
+        // a call sees the globals and what it captured, not the caller's locals
+        let callers = self.env.take_locals();
+        self.env.restore_locals(function.captured.clone());
+        self.env.push_scope();
+        if let Some(name) = &function.name {
+            self.env
+                .define(&name.literal, Value::Function(function.clone()));
+        }
+        for (param, arg) in function.params.iter().zip(args) {
+            if let Expression::Id { name, .. } = &param.expr {
+                self.env.define(&name.literal, arg);
+            }
+        }
+        self.depth += 1;
+        let result = self.exec_stmts(&function.body);
+        self.depth -= 1;
+        self.env.restore_locals(callers);
+
+        match result? {
+            Flow::Return(value) => Ok(value),
+            Flow::Next => Ok(Value::Unit),
+            Flow::Break(_) | Flow::Continue(_) => Err(Self::stray_loop_jump()),
+        }
+    }
+
+    fn exec_for(
+        &mut self,
+        label: Option<&Token>,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) -> Result<Flow, String> {
+        if let Some(init) = init {
+            self.exec(init)?;
+        }
+
+        loop {
+            if let Some(condition) = condition
+                && !self.eval_condition(condition, "for condition")?
+            {
+                break;
+            }
+
+            match self.exec_block(body)? {
+                Flow::Break(target) if Self::is_target(target.as_ref(), label) => break,
+                Flow::Continue(target) if Self::is_target(target.as_ref(), label) => {}
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+
+            if let Some(step) = step {
+                self.eval(step)?;
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    /// Turns the result of a checked `i64` operation into a value, reporting
+    /// overflow instead of wrapping or panicking.
+    fn checked(result: Option<i64>, what: &str, location: &Location) -> Result<Value, String> {
+        result
+            .map(Value::Int)
+            .ok_or_else(|| format!("integer overflow in {} at {}", what, location))
+    }
+
+    /// Whether a jump aimed at `target` stops at the loop labeled `label`.
+    fn is_target(target: Option<&Rc<str>>, label: Option<&Token>) -> bool {
+        match target {
+            Some(target) => label.is_some_and(|label| label.literal == *target),
+            None => true,
+        }
+    }
+
+    fn stray_loop_jump() -> String {
+        "'break' or 'continue' outside of a loop".into()
+    }
+
+    fn apply_binary(op: &Token, lhs: Value, rhs: Value) -> Result<Value, String> {
+        if op.kind.is_one_of(&[TokenKind::Eq2, TokenKind::Ne]) {
+            // values of different types are never silently unequal
+            if lhs.type_name() != rhs.type_name() {
+                return Err(format!(
+                    "cannot compare {} and {} with '{}' at {}",
+                    lhs.type_name(),
+                    rhs.type_name(),
+                    op.kind,
+                    op.location
+                ));
+            }
+
+            return Ok(Value::Bool((lhs == rhs) == op.kind.is(TokenKind::Eq2)));
+        }
+
+        match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => match op.kind {
+                TokenKind::Plus => Self::checked(l.checked_add(r), "addition", &op.location),
+                TokenKind::Minus => Self::checked(l.checked_sub(r), "subtraction", &op.location),
+                TokenKind::Star => Self::checked(l.checked_mul(r), "multiplication", &op.location),
+                TokenKind::Slash => {
+                    if r == 0 {
+                        return Err(format!("division by zero at {}", op.location));
+                    }
+
+                    Self::checked(l.checked_div(r), "division", &op.location)
+                }
+                TokenKind::Percent => {
+                    if r == 0 {
+                        return Err(format!("modulo by zero at {}", op.location));
+                    }
+
+                    Self::checked(l.checked_rem(r), "modulo", &op.location)
+                }
+                TokenKind::Caret => {
+                    let exp = u32::try_from(r)
+                        .map_err(|_| format!("invalid exponent {} at {}", r, op.location))?;
+
+                    Self::checked(l.checked_pow(exp), "exponentiation", &op.location)
+                }
+                TokenKind::Lt => Ok(Value::Bool(l < r)),
+                TokenKind::Gt => Ok(Value::Bool(l > r)),
+                TokenKind::Le => Ok(Value::Bool(l <= r)),
+                TokenKind::Ge => Ok(Value::Bool(l >= r)),
+                _ => Err(format!(
+                    "unsupported binary operator '{}' at {}",
+                    op.kind, op.location
+                )),
+            },
+            (Value::String(l), Value::String(r)) if op.kind.is(TokenKind::Plus) => {
+                Ok(Value::String(l + &r))
+            }
+            (Value::String(s), Value::Int(n)) if op.kind.is(TokenKind::Star) => {
+                let count = usize::try_from(n).map_err(|_| {
+                    format!("cannot repeat a string {} times at {}", n, op.location)
+                })?;
+
+                Ok(Value::String(s.repeat(count)))
+            }
+            (lhs, rhs) => Err(format!(
+                "cannot apply '{}' to {} and {} at {}",
+                op.kind,
+                lhs.type_name(),
+                rhs.type_name(),
+                op.location
+            )),
+        }
+    }
+
+    fn eval_logical(
+        &mut self,
+        lhs: &Expression,
+        op: &Token,
+        rhs: &Expression,
+    ) -> Result<Value, String> {
+        let Value::Bool(lhs) = self.eval(lhs)? else {
+            return Err(format!(
+                "'{}' expects boolean operands at {}",
+                op.kind, op.location
+            ));
+        };
+
+        match (op.kind, lhs) {
+            (TokenKind::AmpAmp, false) => return Ok(Value::Bool(false)),
+            (TokenKind::PipePipe, true) => return Ok(Value::Bool(true)),
+            _ => {}
+        }
+
+        match self.eval(rhs)? {
+            Value::Bool(rhs) => Ok(Value::Bool(rhs)),
+            _ => Err(format!(
+                "'{}' expects boolean operands at {}",
+                op.kind, op.location
+            )),
+        }
+    }
+
+    fn call_builtin(
+        &mut self,
+        name: &Token,
+        args: &[Expression],
+        named_args: &[(Token, Expression)],
+    ) -> Option<Result<Value, String>> {
+        let builtin = match &*name.literal {
+            "print" => Self::builtin_print,
+            "assert" => Self::builtin_assert,
+            _ => return None,
+        };
+
+        if let Some((arg, _)) = named_args.first() {
+            return Some(Err(format!(
+                "builtin '{}' does not take named arguments at {}",
+                name.literal, arg.location
+            )));
+        }
+
+        Some(builtin(self, name, args))
+    }
+
+    fn builtin_print(&mut self, name: &Token, args: &[Expression]) -> Result<Value, String> {
+        let [arg] = args else {
+            return Err(format!(
+                "'print' expects 1 argument, but got {} at {}",
+                args.len(),
+                name.location
+            ));
+        };
+
+        println!("{}", self.eval(arg)?);
+        Ok(Value::Unit)
+    }
+
+    /// `assert(cond)` or `assert(cond, message)`: fails when the condition
+    /// is `false` or `0`.
+    fn builtin_assert(&mut self, name: &Token, args: &[Expression]) -> Result<Value, String> {
+        let (condition, message) = match args {
+            [condition] => (condition, None),
+            [condition, message] => (condition, Some(message)),
+            _ => {
+                return Err(format!(
+                    "'assert' expects 1 or 2 arguments, but got {} at {}",
+                    args.len(),
+                    name.location
+                ));
+            }
+        };
+
+        let passed = match self.eval(condition)? {
+            Value::Bool(v) => v,
+            Value::Int(v) => v != 0,
+            other => {
+                return Err(format!(
+                    "'assert' expects a bool or int condition, found {} at {}",
+                    other.type_name(),
+                    name.location
+                ));
+            }
+        };
+        if passed {
+            return Ok(Value::Unit);
+        }
+
+        match message {
+            Some(message) => Err(format!(
+                "assertion failed: {} at {}",
+                self.eval(message)?,
+                name.location
+            )),
+            None => Err(format!("assertion failed at {}", name.location)),
+        }
+    }
+
+    /// Evaluates the indexes of an assignment target, so that the element it
+    /// names can be read and written without running them again.
+    fn resolve(&mut self, target: &Expression) -> Result<Place, String> {
+        match target {
+            Expression::Id { name, .. } => Ok(Place {
+                name: name.clone(),
+                indexes: Vec::new(),
+            }),
+            Expression::Grouping { inner, .. } => self.resolve(inner),
+            Expression::Index { target, index, .. } => {
+                let mut place = self.resolve(target)?;
+                let i = self.eval(index)?;
+                place.indexes.push((i, index.span().start.clone()));
+                Ok(place)
+            }
+            _ => Err(format!(
+                "cannot assign to '{}' at {}",
+                target,
+                target.span().start
+            )),
+        }
+    }
+
+    /// The storage a resolved target refers to: a variable, or an array
+    /// element reached through any number of indexes.
+    fn place(&mut self, place: &Place) -> Result<&mut Value, String> {
+        let name = &place.name;
+        let mut value = self.env.get_mut(&name.literal).ok_or_else(|| {
+            format!(
+                "undefined identifier '{}' at {}",
+                name.literal, name.location
+            )
+        })?;
+
+        for (i, location) in &place.indexes {
+            value = match value {
+                Value::Array(elements) => {
+                    let i = Self::index_of(i, elements.len(), location)?;
+                    &mut elements[i]
+                }
+                value => {
+                    return Err(format!(
+                        "cannot index a value of type {} at {}",
+                        value.type_name(),
+                        location
+                    ));
+                }
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn index_of(index: &Value, len: usize, location: &Location) -> Result<usize, String> {
+        let Value::Int(i) = index else {
+            return Err(format!(
+                "array index must be an int, but got {} at {}",
+                index.type_name(),
+                location
+            ));
+        };
+
+        usize::try_from(*i)
+            .ok()
+            .filter(|i| *i < len)
+            .ok_or_else(|| {
+                format!(
+                    "index {} is out of bounds for an array of length {} at {}",
+                    i, len, location
+                )
+            })
+    }
+
+    fn unsupported(what: &str) -> Result<Value, String> {
+        Err(format!("{} is not supported by the interpreter yet", what))
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SVisitor<Flow> for Interpreter {
+    fn visit_return(&mut self, value: Option<&Expression>, _span: &Span) -> Result<Flow, String> {
+        let value = match value {
+            Some(expr) => self.eval(expr)?,
+            None => Value::Unit,
+        };
+
+        Ok(Flow::Return(value))
+    }
+
+    fn visit_let(
+        &mut self,
+        name: &Token,
+        _type_annotation: Option<&Type>,
+        value: &Expression,
+    ) -> Result<Flow, String> {
+        let value = self.eval(value)?;
+        self.env.define(&name.literal, value);
+
+        Ok(Flow::Next)
+    }
+
+    fn visit_const(
+        &mut self,
+        name: &Token,
+        type_annotation: Option<&Type>,
+        value: &Expression,
+    ) -> Result<Flow, String> {
+        self.visit_let(name, type_annotation, value)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expression,
+        then_branch: &[Statement],
+        else_branch: Option<&[Statement]>,
+    ) -> Result<Flow, String> {
+        if self.eval_condition(condition, "if condition")? {
+            return self.exec_block(then_branch);
+        }
+
+        match else_branch {
+            Some(else_branch) => self.exec_block(else_branch),
+            None => Ok(Flow::Next),
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        label: Option<&Token>,
+        condition: &Expression,
+        body: &[Statement],
+    ) -> Result<Flow, String> {
+        while self.eval_condition(condition, "while condition")? {
+            match self.exec_block(body)? {
+                Flow::Break(target) if Self::is_target(target.as_ref(), label) => break,
+                Flow::Continue(target) if Self::is_target(target.as_ref(), label) => {}
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn visit_for(
+        &mut self,
+        label: Option<&Token>,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) -> Result<Flow, String> {
+        self.env.push_scope();
+        let result = self.exec_for(label, init, condition, step, body);
+        self.env.pop_scope();
+
+        result
+    }
+
+    fn visit_break(&mut self, label: Option<&Token>, _span: &Span) -> Result<Flow, String> {
+        Ok(Flow::Break(label.map(|label| label.literal.clone())))
+    }
+
+    fn visit_continue(&mut self, label: Option<&Token>, _span: &Span) -> Result<Flow, String> {
+        Ok(Flow::Continue(label.map(|label| label.literal.clone())))
+    }
+
+    fn visit_struct_decl(
+        &mut self,
+        _name: &Token,
+        _fields: &[TypeMapping],
+    ) -> Result<Flow, String> {
+        Ok(Flow::Next)
+    }
+
+    fn visit_expression_stmt(&mut self, expr: &Expression) -> Result<Flow, String> {
+        self.eval(expr)?;
+        Ok(Flow::Next)
+    }
+
+    fn visit_import(&mut self, path: &Token) -> Result<Flow, String> {
+        Err(format!(
+            "unresolved import {} at {}",
+            path.literal, path.location
+        ))
+    }
+
+    fn visit_empty(&mut self) -> Result<Flow, String> {
+        Ok(Flow::Next)
+    }
+
+    fn visit_halt(&mut self) -> Result<Flow, String> {
+        Ok(Flow::Next)
+    }
+}
+
+impl EVisitor<Value> for Interpreter {
+    fn visit_binary(
+        &mut self,
+        lhs: &Expression,
+        op: &Token,
+        rhs: &Expression,
+    ) -> Result<Value, String> {
+        if op.kind.is_one_of(&[TokenKind::AmpAmp, TokenKind::PipePipe]) {
+            return self.eval_logical(lhs, op, rhs);
+        }
+
+        let lhs = self.eval(lhs)?;
+        let rhs = self.eval(rhs)?;
+
+        Self::apply_binary(op, lhs, rhs)
+    }
+
+    fn visit_unary(&mut self, op: &Token, expr: &Expression) -> Result<Value, String> {
+        match (op.kind, self.eval(expr)?) {
+            (TokenKind::Plus, Value::Int(v)) => Ok(Value::Int(v)),
+            (TokenKind::Minus, Value::Int(v)) => {
+                Self::checked(v.checked_neg(), "negation", expr.location())
+            }
+            (TokenKind::Bang, Value::Bool(v)) => Ok(Value::Bool(!v)),
+            _ => Err(format!(
+                "unsupported unary operator '{}' at {}",
+                op.kind, op.location
+            )),
+        }
+    }
+
+    fn visit_ternary(
+        &mut self,
+        condition: &Expression,
+        then_expr: &Expression,
+        else_expr: &Expression,
+    ) -> Result<Value, String> {
+        if self.eval_condition(condition, "ternary condition")? {
+            return self.eval(then_expr);
+        }
+
+        self.eval(else_expr)
+    }
+
+    fn visit_assign(
+        &mut self,
+        target: &Expression,
+        op: Option<&Token>,
+        value: &Expression,
+    ) -> Result<Value, String> {
+        let Some(op) = op else {
+            let value = self.eval(value)?;
+            let place = self.resolve(target)?;
+            *self.place(&place)? = value.clone();
+
+            return Ok(value);
+        };
+
+        // `a[f()] += 1` calls `f` once, for both the read and the write
+        let place = self.resolve(target)?;
+        let current = self.place(&place)?.clone();
+        let rhs = self.eval(value)?;
+        let value = Self::apply_binary(op, current, rhs)?;
+        *self.place(&place)? = value.clone();
+
+        Ok(value)
+    }
+
+    fn visit_function_call(
+        &mut self,
+        callee: &Expression,
+        args: &[Expression],
+        named_args: &[(Token, Expression)],
+    ) -> Result<Value, String> {
+        if let Expression::Id { name, .. } = callee.ungrouped()
+            && self.env.get(&name.literal).is_none()
+            && let Some(result) = self.call_builtin(name, args, named_args)
+        {
+            return result;
+        }
+
+        let Value::Function(function) = self.eval(callee)? else {
+            return Err(format!(
+                "cannot call a non-function value at {}",
+                callee.location()
+            ));
+        };
+
+        let mut args = args
+            .iter()
+            .map(|arg| self.eval(arg))
+            .collect::<Result<Vec<Value>, String>>()?;
+
+        if !named_args.is_empty() {
+            let named = named_args
+                .iter()
+                .map(|(name, arg)| Ok((name, self.eval(arg)?)))
+                .collect::<Result<Vec<(&Token, Value)>, String>>()?;
+            args = order_args(&function.params, args, named, &callee.span().start)
+                .map_err(|err| err.to_string())?;
+        }
+
+        self.call(callee, function, args)
+    }
+
+    fn visit_function_literal(
+        &mut self,
+        name: &Option<Token>,
+        params: &[TypeMapping],
+        _return_type: &Option<Type>,
+        body: &[Statement],
+    ) -> Result<Value, String> {
+        let function = Value::Function(Rc::new(Function {
+            name: name.clone(),
+            params: params.to_vec(),
+            body: body.to_vec(),
+            captured: self.env.locals(),
+        }));
+
+        if let Some(name) = name {
+            self.env.define(&name.literal, function.clone());
+        }
+
+        Ok(function)
+    }
+
+    fn visit_array(&mut self, elements: &[Expression], _span: &Span) -> Result<Value, String> {
+        elements
+            .iter()
+            .map(|e| self.eval(e))
+            .collect::<Result<Vec<Value>, String>>()
+            .map(Value::Array)
+    }
+
+    fn visit_grouping(&mut self, inner: &Expression) -> Result<Value, String> {
+        self.eval(inner)
+    }
+
+    fn visit_tuple(&mut self, _elements: &[Expression]) -> Result<Value, String> {
+        Self::unsupported("tuple")
+    }
+
+    fn visit_index(&mut self, target: &Expression, index: &Expression) -> Result<Value, String> {
+        let target_value = self.eval(target)?;
+        let i = self.eval(index)?;
+        let location = &index.span().start;
+
+        match target_value {
+            Value::Array(mut elements) => {
+                let i = Self::index_of(&i, elements.len(), location)?;
+                Ok(elements.swap_remove(i))
+            }
+            value => Err(format!(
+                "cannot index a value of type {} at {}",
+                value.type_name(),
+                location
+            )),
+        }
+    }
+
+    fn visit_member(&mut self, _target: &Expression, _field: &Token) -> Result<Value, String> {
+        Self::unsupported("member access")
+    }
+
+    fn visit_struct_literal(
+        &mut self,
+        _name: &Token,
+        _fields: &[(Token, Expression)],
+    ) -> Result<Value, String> {
+        Self::unsupported("struct literal")
+    }
+
+    fn visit_id(&mut self, name: Token) -> Result<Value, String> {
+        self.env.get(&name.literal).cloned().ok_or_else(|| {
+            format!(
+                "undefined identifier '{}' at {}",
+                name.literal, name.location
+            )
+        })
+    }
+
+    fn visit_int(&mut self, value: Token) -> Result<Value, String> {
+        value.int_value().map(Value::Int)
+    }
+
+    fn visit_float(&mut self, _value: Token) -> Result<Value, String> {
+        Self::unsupported("float literal")
+    }
+
+    fn visit_str(&mut self, value: Token) -> Result<Value, String> {
+        value
+            .unescaped()
+            .map(Value::String)
+            .map_err(|err| format!("{} at {}", err, value.location))
+    }
+
+    fn visit_char(&mut self, _value: Token) -> Result<Value, String> {
+        Self::unsupported("character literal")
+    }
+
+    fn visit_bool(&mut self, value: Token) -> Result<Value, String> {
+        Ok(Value::Bool(value.kind.is(TokenKind::True)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// Runs every statement of `src` and evaluates the last one, which must
+    /// be an expression statement.
+    fn run(src: &str) -> Result<Value, String> {
+        let tokens = Lexer::from_str(src).lex().expect("source should lex");
+        let stmts = Parser::new(tokens).parse().expect("source should parse");
+        let Some((Statement::ExpressionStatement { expression, .. }, init)) = stmts.split_last()
+        else {
+            panic!("expected an expression statement last, got {:?}", stmts);
+        };
+
+        let mut interpreter = Interpreter::new();
+        interpreter.run(init)?;
+        interpreter.eval(expression)
+    }
+
+    #[test]
+    fn evaluates_integer_arithmetic() {
+        assert!(matches!(run("2 + 3 * 4;"), Ok(Value::Int(14))));
+        assert!(matches!(run("-(1 - 8) / +2;"), Ok(Value::Int(3))));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert!(run("10 / 0;").is_err());
+    }
+
+    #[test]
+    fn unsupported_unary_operator_is_located() {
+        let tokens = Lexer::from_str("  * 1").lex().expect("source should lex");
+        let expr = Expression::Unary {
+            op: tokens[0].clone(),
+            expr: Expression::Int {
+                value: tokens[1].clone(),
+                span: tokens[1].span(),
+            }
+            .into(),
+            span: tokens[0].span().to(&tokens[1].span()),
+        };
+        assert_eq!(
+            Interpreter::new().eval(&expr).unwrap_err(),
+            "unsupported unary operator '*' at line: 1, col: 3"
+        );
+    }
+
+    #[test]
+    fn division_by_zero_reports_the_operator() {
+        assert_eq!(
+            run("1 +\n  10 / 0;").unwrap_err(),
+            "division by zero at line: 2, col: 6"
+        );
+    }
+
+    #[test]
+    fn print_returns_unit() {
+        assert!(matches!(run("print(1 + 2);"), Ok(Value::Unit)));
+        assert_eq!(
+            run("print(1, 2);").unwrap_err(),
+            "'print' expects 1 argument, but got 2 at line: 1, col: 1"
+        );
+    }
+
+    #[test]
+    fn calls_recursive_functions() {
+        let src =
+            "fn fact(n: int) -> int { if (n < 2) { return 1; } return n * fact(n - 1); } fact(10);";
+        assert!(matches!(run(src), Ok(Value::Int(3_628_800))));
+    }
+
+    #[test]
+    fn wrong_arity_is_an_error() {
+        assert_eq!(
+            run("fn f(a: int) -> int { return a; } f(1, 2);").unwrap_err(),
+            "expected 1 arguments, but got 2 at line: 1, col: 35"
+        );
+    }
+
+    #[test]
+    fn unbounded_recursion_is_an_error() {
+        // the default test thread's stack is too small for the full depth
+        let run = || run("fn f(n: int) -> int { return f(n + 1); } f(0);").unwrap_err();
+        let err = std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(run)
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(
+            err,
+            "maximum call depth of 512 exceeded at line: 1, col: 30"
+        );
+    }
+
+    #[test]
+    fn non_bool_condition_names_the_type() {
+        assert_eq!(
+            run("if (1) { 2; } 3;").unwrap_err(),
+            "if condition must be a bool, found int at line: 1, col: 5"
+        );
+    }
+
+    #[test]
+    fn chained_assignment_sets_every_target() {
+        assert!(matches!(
+            run("let x = 0; let y = 0; x = y = 3; x + y;"),
+            Ok(Value::Int(6))
+        ));
+    }
+
+    #[test]
+    fn functions_see_captured_locals_not_the_callers() {
+        let src = "fn outer() -> int { let a = 1; let f = fn () -> int { return a; }; let a = 2; return f(); } outer();";
+        assert!(matches!(run(src), Ok(Value::Int(1))));
+
+        let src =
+            "let g = fn () -> int { return b; }; fn h() -> int { let b = 5; return g(); } h();";
+        assert!(run(src).is_err());
+    }
+
+    #[test]
+    fn evaluates_compound_assignment() {
+        assert!(matches!(
+            run("let x = 1; x += 2 * 3; x *= 2; x;"),
+            Ok(Value::Int(14))
+        ));
+    }
+
+    #[test]
+    fn evaluates_nested_prefix_operators() {
+        assert!(matches!(run("- - 2;"), Ok(Value::Int(2))));
+        assert!(matches!(run("-+-3;"), Ok(Value::Int(3))));
+    }
+
+    #[test]
+    fn displays_values() {
+        let values = [
+            Value::Int(42),
+            Value::Bool(true),
+            Value::String("hi".into()),
+            Value::Array(vec![Value::Int(1), Value::Int(2)]),
+            Value::Unit,
+        ];
+        let shown: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(shown, ["42", "true", "hi", "[1, 2]", "void"]);
+    }
+
+    #[test]
+    fn runs_for_loops() {
+        let src = "let sum = 0; for (let i = 1; i <= 4; i += 1) { sum += i; } sum;";
+        assert!(matches!(run(src), Ok(Value::Int(10))));
+    }
+
+    #[test]
+    fn integer_overflow_is_an_error() {
+        assert_eq!(
+            run("4611686018427387904 * 2;").unwrap_err(),
+            "integer overflow in multiplication at line: 1, col: 21"
+        );
+        assert_eq!(
+            run("9223372036854775807 + 1;").unwrap_err(),
+            "integer overflow in addition at line: 1, col: 21"
+        );
+        assert_eq!(
+            run("-9223372036854775807 - 2;").unwrap_err(),
+            "integer overflow in subtraction at line: 1, col: 22"
+        );
+        assert!(matches!(
+            run("4611686018427387903 * 2 + 1;"),
+            Ok(Value::Int(i64::MAX))
+        ));
+    }
+
+    #[test]
+    fn evaluates_only_the_chosen_branch() {
+        assert!(matches!(
+            run("let a = 1; a > 0 ? 10 : 1 / 0;"),
+            Ok(Value::Int(10))
+        ));
+        assert!(matches!(
+            run("false ? 1 : true ? 2 : 3;"),
+            Ok(Value::Int(2))
+        ));
+    }
+
+    #[test]
+    fn binds_named_arguments_by_name() {
+        let src = "fn sub(a: int, b: int) -> int { return a - b; } sub(b: 1, a: 10);";
+        assert!(matches!(run(src), Ok(Value::Int(9))));
+    }
+
+    #[test]
+    fn concatenates_strings() {
+        assert!(matches!(run(r#""a" + "b";"#), Ok(Value::String(s)) if s == "ab"));
+        assert!(matches!(run(r#""x\ty" + "";"#), Ok(Value::String(s)) if s == "x\ty"));
+        assert!(matches!(run(r#""ab" * 2;"#), Ok(Value::String(s)) if s == "abab"));
+    }
+
+    #[test]
+    fn adding_a_string_to_an_int_is_an_error() {
+        assert_eq!(
+            run(r#"1 + "b";"#).unwrap_err(),
+            "cannot apply '+' to int and string at line: 1, col: 3"
+        );
+    }
+
+    #[test]
+    fn compares_values_of_the_same_type() {
+        assert_eq!(run("1 == 1;"), Ok(Value::Bool(true)));
+        assert_eq!(run("1 != 2;"), Ok(Value::Bool(true)));
+        assert_eq!(run("true == false;"), Ok(Value::Bool(false)));
+        assert_eq!(run("2 < 3;"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn comparing_different_types_is_an_error() {
+        assert_eq!(
+            run(r#"1 == "1";"#),
+            Err("cannot compare int and string with '==' at line: 1, col: 3".to_string())
+        );
+        assert_eq!(
+            run("true < false;"),
+            Err("cannot apply '<' to bool and bool at line: 1, col: 6".to_string())
+        );
+    }
+
+    #[test]
+    fn calls_the_function_a_call_returns() {
+        let src = "fn adder() -> fn(int) -> int { return fn (x: int) -> int { return x + 1; }; } adder()(41);";
+        assert_eq!(run(src), Ok(Value::Int(42)));
+    }
+
+    #[test]
+    fn void_functions_return_unit() {
+        assert_eq!(run("fn f() { return; } f();"), Ok(Value::Unit));
+        assert_eq!(run("fn g() {} g();"), Ok(Value::Unit));
+    }
+
+    #[test]
+    fn stores_into_array_elements() {
+        assert_eq!(
+            run("let a = [1, 2, 3]; a[1] = 5; a;"),
+            Ok(Value::Array(vec![
+                Value::Int(1),
+                Value::Int(5),
+                Value::Int(3),
+            ]))
+        );
+        assert_eq!(
+            run("let a = [[0], [0]]; a[1][0] = 7; a[1][0];"),
+            Ok(Value::Int(7))
+        );
+    }
+
+    #[test]
+    fn indexes_nested_arrays() {
+        assert_eq!(
+            run("let a = [[1, 2], [3, 4]]; a[1][0];"),
+            Ok(Value::Int(3))
+        );
+    }
+
+    #[test]
+    fn compound_assignment_evaluates_the_index_once() {
+        let src = "let n = 0; fn f() -> int { n = n + 1; return 0; } \
+                   let a = [1, 2]; a[f()] += 1; n;";
+        assert_eq!(run(src), Ok(Value::Int(1)));
+        assert_eq!(
+            run("let a = [1, 2]; a[1] *= 5; a[0] -= 1; a;"),
+            Ok(Value::Array(vec![Value::Int(0), Value::Int(10)]))
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_store_is_an_error() {
+        assert_eq!(
+            run("let a = [1]; a[3] = 2;"),
+            Err(
+                "index 3 is out of bounds for an array of length 1 at line: 1, col: 16".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn labeled_break_leaves_the_outer_loop() {
+        let src =
+            "let n = 0; outer: while (true) { while (true) { n += 1; break outer; } n = 100; } n;";
+        assert_eq!(run(src), Ok(Value::Int(1)));
+    }
+
+    #[test]
+    fn evaluates_the_grouped_expression() {
+        assert_eq!(run("(1 + 2) * 3;"), Ok(Value::Int(9)));
+    }
+
+    #[test]
+    fn passing_assert_returns_unit() {
+        assert_eq!(run("assert(1 < 2); assert(1, \"fine\");"), Ok(Value::Unit));
+    }
+
+    #[test]
+    fn failing_assert_is_an_error() {
+        assert_eq!(
+            run("\nassert(0);"),
+            Err("assertion failed at line: 2, col: 1".to_string())
+        );
+        assert_eq!(
+            run(r#"assert(1 > 2, "one is small");"#),
+            Err("assertion failed: one is small at line: 1, col: 1".to_string())
+        );
+    }
+}
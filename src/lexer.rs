@@ -1,76 +1,167 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::error::CompileError;
 use crate::tokens::{Location, Token, TokenKind};
 
-pub struct Lexer {
-    src: Vec<char>,
+pub struct Lexer<'a> {
+    src: Cow<'a, str>,
     pos: usize,
     location: Location,
-    tokens: Vec<Token>,
+    prev_location: Location,
+    finished: bool,
+    symbols: HashSet<Rc<str>>,
+    keep_comments: bool,
+    comments: Vec<Token>,
+    tab_width: usize,
 }
 
-impl Lexer {
+impl Lexer<'static> {
     pub fn new(src: String) -> Self {
+        Self::from_source(Cow::Owned(src))
+    }
+}
+
+impl<'a> Lexer<'a> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(src: &'a str) -> Self {
+        Self::from_source(Cow::Borrowed(src))
+    }
+
+    fn from_source(src: Cow<'a, str>) -> Self {
         Self {
-            src: src.chars().collect(),
+            src,
             pos: 0,
             location: Location::new(1, 1),
-            tokens: Vec::new(),
+            prev_location: Location::new(1, 1),
+            finished: false,
+            symbols: HashSet::new(),
+            keep_comments: false,
+            comments: Vec::new(),
+            tab_width: 1,
         }
     }
 
-    pub fn lex(&mut self) -> Result<Vec<Token>, String> {
-        while self.pos < self.src.len() {
-            self.skip_whitespaces();
-            self.skip_comments();
-            if self.pos >= self.src.len() {
-                break;
+    /// Collects comments as `TokenKind::Comment` tokens, available from
+    /// `comments()` after lexing. They never appear in the main token stream.
+    pub fn with_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
+    }
+
+    /// Counts a `\t` as `tab_width` columns, so reported columns match an
+    /// editor that expands tabs. Defaults to 1.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    pub fn comments(&self) -> &[Token] {
+        &self.comments
+    }
+
+    pub fn lex(&mut self) -> Result<Vec<Token>, Vec<CompileError>> {
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut errs: Vec<CompileError> = Vec::new();
+
+        for result in self.lex_all() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(err) => errs.push(err),
             }
+        }
 
-            let curr = self.curr();
+        if !errs.is_empty() {
+            return Err(errs);
+        }
 
-            if let Some(token) = self.lex_single_char_token() {
-                self.tokens.push(token);
+        Ok(tokens)
+    }
+
+    /// Lexes lazily, one token per step, ending with `EOF`. Unlike `lex`,
+    /// errors are yielded in place and lexing carries on after them.
+    pub fn lex_all(&mut self) -> Tokens<'_, 'a> {
+        Tokens { lexer: self }
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token, CompileError>> {
+        if self.finished {
+            return None;
+        }
+
+        self.skip_whitespaces();
+        if let Err(err) = self.skip_comments() {
+            return Some(Err(err));
+        }
+
+        if self.pos >= self.src.len() {
+            self.finished = true;
+            return Some(Ok(Token::new(
+                TokenKind::EOF,
+                "".into(),
+                self.location.clone(),
+                self.location.clone(),
+            )));
+        }
+
+        let curr = self.curr();
+
+        if let Some(token) = self.lex_single_char_token() {
+            for _ in 0..Self::operator_width(token.kind) {
                 self.advance();
-                continue;
             }
+            return Some(Ok(token));
+        }
 
-            if curr == '"' {
-                let token = self.lex_double_quoted_string()?;
-                self.tokens.push(token);
-                continue;
-            }
+        if curr == '"' {
+            return Some(self.lex_double_quoted_string());
+        }
 
-            if curr.is_alphabetic() || curr == '_' {
-                if let Some(token) = self.lex_id_or_keyword() {
-                    self.tokens.push(token);
-                    continue;
-                }
-            }
+        if curr == '\'' {
+            return Some(self.lex_single_quoted_char());
+        }
 
-            if curr.is_numeric() {
-                if let Some(token) = self.lex_number() {
-                    self.tokens.push(token);
-                    continue;
-                }
-            }
+        if Self::is_id_start(curr)
+            && let Some(token) = self.lex_id_or_keyword()
+        {
+            return Some(Ok(token));
+        }
 
-            return Err(format!("unrecognized lexeme at {}", self.location));
+        if curr.is_ascii_digit() {
+            return Some(self.lex_number());
         }
 
-        self.tokens
-            .push(Token::new(TokenKind::EOF, "".into(), self.location.clone()));
-        Ok(self.tokens.clone())
+        let err = CompileError::lexical(
+            format!("unrecognized character '{}'", curr),
+            self.location.clone(),
+        );
+        self.advance();
+        Some(Err(err))
     }
 
     fn lex_single_char_token(&self) -> Option<Token> {
         let kind = match self.curr() {
-            '*' => TokenKind::Star,
-            '/' => TokenKind::Slash,
-            '+' => {
-                if self.peek() == '+' {
-                    TokenKind::Inc
+            '*' => {
+                if self.peek() == '=' {
+                    TokenKind::StarEq
+                } else {
+                    TokenKind::Star
+                }
+            }
+            '/' => {
+                if self.peek() == '=' {
+                    TokenKind::SlashEq
                 } else {
-                    TokenKind::Plus
+                    TokenKind::Slash
                 }
+            }
+            '%' => TokenKind::Percent,
+            '^' => TokenKind::Caret,
+            '+' => match self.peek() {
+                '+' => TokenKind::Inc,
+                '=' => TokenKind::PlusEq,
+                _ => TokenKind::Plus,
             },
             '=' => {
                 if self.peek() == '=' {
@@ -79,31 +170,93 @@ impl Lexer {
                     TokenKind::Eq
                 }
             },
-            '-' => {
-                if self.peek() == '-' {
-                    TokenKind::Decr
+            '-' => match self.peek() {
+                '-' => TokenKind::Decr,
+                '>' => TokenKind::Arrow,
+                '=' => TokenKind::MinusEq,
+                _ => TokenKind::Minus,
+            },
+            '<' => {
+                if self.peek() == '=' {
+                    TokenKind::Le
+                } else {
+                    TokenKind::Lt
+                }
+            }
+            '>' => {
+                if self.peek() == '=' {
+                    TokenKind::Ge
                 } else {
-                    TokenKind::Minus
+                    TokenKind::Gt
                 }
             }
+            '!' => {
+                if self.peek() == '=' {
+                    TokenKind::Ne
+                } else {
+                    TokenKind::Bang
+                }
+            }
+            '&' if self.peek() == '&' => TokenKind::AmpAmp,
+            '|' if self.peek() == '|' => TokenKind::PipePipe,
+            '?' => TokenKind::Question,
             ':' => TokenKind::Colon,
             ';' => TokenKind::Semicolon,
             '(' => TokenKind::LParen,
             ')' => TokenKind::RParen,
             '{' => TokenKind::LCurly,
             '}' => TokenKind::RCurly,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
             ',' => TokenKind::Comma,
+            '.' => TokenKind::Dot,
             _ => {
                 return None;
             }
         };
 
-        Token::new(kind, self.curr().into(), self.location.clone()).into()
+        let width = Self::operator_width(kind);
+        let literal: String = (0..width).map(|offset| self.peek_off(offset)).collect();
+
+        let mut end = self.location.clone();
+        for ch in literal.chars().take(width - 1) {
+            end.add_char(ch);
+        }
+
+        Token::new(kind, literal.into(), self.location.clone(), end).into()
+    }
+
+    /// Number of source characters an operator or punctuation token spans;
+    /// `lex` advances by exactly this many after emitting it.
+    fn operator_width(kind: TokenKind) -> usize {
+        match kind {
+            TokenKind::Inc
+            | TokenKind::Decr
+            | TokenKind::Arrow
+            | TokenKind::PlusEq
+            | TokenKind::MinusEq
+            | TokenKind::StarEq
+            | TokenKind::SlashEq
+            | TokenKind::Eq2
+            | TokenKind::Ne
+            | TokenKind::Le
+            | TokenKind::Ge
+            | TokenKind::AmpAmp
+            | TokenKind::PipePipe => 2,
+            _ => 1,
+        }
     }
 
-    fn lex_double_quoted_string(&mut self) -> Result<Token, String> {
+    fn lex_double_quoted_string(&mut self) -> Result<Token, CompileError> {
         if self.curr() != '"' {
-            return Err("String must start from \"".into());
+            return Err(CompileError::lexical(
+                "String must start from \"".into(),
+                self.location.clone(),
+            ));
+        }
+
+        if self.peek() == '"' && self.peek_off(2) == '"' {
+            return self.lex_triple_quoted_string();
         }
 
         let location = self.location.clone();
@@ -112,22 +265,141 @@ impl Lexer {
         literal.push(self.curr());
         self.advance();
 
+        let mut closed = false;
+
         while self.pos < self.src.len() {
             let curr = self.curr();
-            if curr == '\n' {
-                return Err("Unclosed string".into());
+            if Self::is_line_break(curr) {
+                break;
             }
 
             literal.push(curr);
             if curr == '"' && !Self::is_escaped(&literal) {
                 self.advance();
+                closed = true;
                 break;
             }
 
             self.advance();
         }
 
-        Ok(Token::new(TokenKind::String, literal, location))
+        if !closed {
+            return Err(CompileError::lexical("Unclosed string".into(), location));
+        }
+
+        let token = Token::new(
+            TokenKind::String,
+            literal.into(),
+            location,
+            self.prev_location.clone(),
+        );
+        token
+            .unescaped()
+            .map_err(|err| CompileError::lexical(err, token.location.clone()))?;
+
+        Ok(token)
+    }
+
+    fn lex_triple_quoted_string(&mut self) -> Result<Token, CompileError> {
+        let location = self.location.clone();
+        let mut literal = String::new();
+
+        for _ in 0..3 {
+            literal.push(self.curr());
+            self.advance();
+        }
+
+        let mut closed = false;
+
+        while self.pos < self.src.len() {
+            if self.curr() == '"' && self.peek() == '"' && self.peek_off(2) == '"' {
+                for _ in 0..3 {
+                    literal.push(self.curr());
+                    self.advance();
+                }
+                closed = true;
+                break;
+            }
+
+            literal.push(self.curr());
+            self.advance();
+        }
+
+        if !closed {
+            return Err(CompileError::lexical("Unclosed string".into(), location));
+        }
+
+        let token = Token::new(
+            TokenKind::String,
+            literal.into(),
+            location,
+            self.prev_location.clone(),
+        );
+        token
+            .unescaped()
+            .map_err(|err| CompileError::lexical(err, token.location.clone()))?;
+
+        Ok(token)
+    }
+
+    fn lex_single_quoted_char(&mut self) -> Result<Token, CompileError> {
+        if self.curr() != '\'' {
+            return Err(CompileError::lexical(
+                "Char must start from '".into(),
+                self.location.clone(),
+            ));
+        }
+
+        let location = self.location.clone();
+        let mut literal = String::new();
+
+        literal.push(self.curr());
+        self.advance();
+
+        if self.curr() == '\'' {
+            self.advance();
+            return Err(CompileError::lexical(
+                "empty character literal".into(),
+                location,
+            ));
+        }
+
+        if self.curr() == '\\' {
+            literal.push(self.curr());
+            self.advance();
+        }
+
+        if self.pos >= self.src.len() || Self::is_line_break(self.curr()) {
+            return Err(CompileError::lexical(
+                "unterminated character literal".into(),
+                location,
+            ));
+        }
+
+        literal.push(self.curr());
+        self.advance();
+
+        if self.pos >= self.src.len() || self.curr() != '\'' {
+            return Err(CompileError::lexical(
+                "unterminated character literal".into(),
+                location,
+            ));
+        }
+
+        literal.push(self.curr());
+        self.advance();
+
+        let token = Token::new(
+            TokenKind::Char,
+            literal.into(),
+            location,
+            self.prev_location.clone(),
+        );
+        token
+            .unescaped()
+            .map_err(|err| CompileError::lexical(err, token.location.clone()))?;
+
+        Ok(token)
     }
 
     fn is_escaped(literal: &str) -> bool {
@@ -145,43 +417,67 @@ impl Lexer {
     }
 
     fn lex_id_or_keyword(&mut self) -> Option<Token> {
-        if !(self.curr().is_alphabetic() || self.curr() == '_') {
+        if !Self::is_id_start(self.curr()) {
             return None;
         }
 
         let location = self.location.clone();
         let mut literal = String::new();
 
-        while self.curr().is_alphanumeric() || self.curr() == '_' {
+        while Self::is_id_continue(self.curr()) {
             literal.push(self.curr());
             self.advance();
         }
 
         let kind = Self::keyword_or_id_kind(&literal);
+        let literal = self.intern(&literal);
 
-        Token::new(kind, literal, location).into()
+        Token::new(kind, literal, location, self.prev_location.clone()).into()
     }
 
-    fn keyword_or_id_kind(literal: &str) -> TokenKind {
-        match literal {
-            "return" => TokenKind::Return,
-            "fn" => TokenKind::Fn,
-            "int" => TokenKind::Int,
-            _ => TokenKind::Id,
-        }
+    /// Identifiers follow Unicode's XID_Start/XID_Continue rules, with `_`
+    /// also allowed as a first character: `café` and `_x1` are identifiers,
+    /// while emoji and other symbols are rejected.
+    fn is_id_start(ch: char) -> bool {
+        ch == '_' || unicode_ident::is_xid_start(ch)
     }
 
-    fn lex_number(&mut self) -> Option<Token> {
-        if !self.curr().is_numeric() {
-            return None;
+    fn is_id_continue(ch: char) -> bool {
+        unicode_ident::is_xid_continue(ch)
+    }
+
+    /// Returns the shared copy of `text`, so a name used many times is only
+    /// allocated once.
+    fn intern(&mut self, text: &str) -> Rc<str> {
+        if let Some(symbol) = self.symbols.get(text) {
+            return symbol.clone();
         }
 
+        let symbol: Rc<str> = text.into();
+        self.symbols.insert(symbol.clone());
+        symbol
+    }
+
+    fn keyword_or_id_kind(literal: &str) -> TokenKind {
+        TokenKind::keyword(literal).unwrap_or(TokenKind::Id)
+    }
+
+    fn lex_number(&mut self) -> Result<Token, CompileError> {
         let location = self.location.clone();
+
+        if self.curr() == '0' && matches!(self.peek(), 'x' | 'o' | 'b') {
+            return self.lex_radix_number(location);
+        }
+
         let mut literal = String::new();
         let mut is_float = false;
+        let mut extra_dot: Option<Location> = None;
 
-        while self.curr().is_numeric() || self.curr() == '_' || self.curr() == '.' {
+        while self.curr().is_ascii_digit() || self.curr() == '_' || self.is_decimal_point() {
             if self.curr() == '.' {
+                if is_float && extra_dot.is_none() {
+                    extra_dot = Some(self.location.clone());
+                }
                 is_float = true;
             }
 
@@ -189,60 +485,234 @@ impl Lexer {
             self.advance();
         }
 
+        if let Some(at) = extra_dot {
+            return Err(CompileError::lexical(
+                format!("unexpected second '.' in number '{}'", literal),
+                at,
+            ));
+        }
+
+        if literal.ends_with('.') {
+            return Err(CompileError::lexical(
+                format!("expected digits after '.' in number '{}'", literal),
+                self.prev_location.clone(),
+            ));
+        }
+
+        if matches!(self.curr(), 'e' | 'E') {
+            is_float = true;
+            self.lex_exponent(&mut literal)?;
+        }
+
+        if Self::is_id_continue(self.curr()) {
+            while Self::is_id_continue(self.curr()) {
+                literal.push(self.curr());
+                self.advance();
+            }
+
+            return Err(CompileError::lexical(
+                format!("identifiers cannot start with a digit: '{}'", literal),
+                location,
+            ));
+        }
+
+        Self::check_underscores(&literal, 0, &location)?;
+
         let mut kind = TokenKind::Int;
 
         if is_float {
             kind = TokenKind::Float;
         }
 
-        Token::new(kind, literal, location).into()
+        Ok(Token::new(kind, literal.into(), location, self.prev_location.clone()))
     }
 
-    fn curr(&self) -> char {
-        if self.pos >= self.src.len() {
-            return 0 as char;
+    // `x[0].len` is a member access, not the float `0.` followed by `len`
+    fn is_decimal_point(&self) -> bool {
+        self.curr() == '.' && !Self::is_id_start(self.peek())
+    }
+
+    fn lex_exponent(&mut self, literal: &mut String) -> Result<(), CompileError> {
+        literal.push(self.curr());
+        self.advance();
+
+        if matches!(self.curr(), '+' | '-') {
+            literal.push(self.curr());
+            self.advance();
+        }
+
+        if !self.curr().is_ascii_digit() {
+            return Err(CompileError::lexical(
+                format!("missing exponent digits in number '{}'", literal),
+                self.location.clone(),
+            ));
+        }
+
+        while self.curr().is_ascii_digit() || self.curr() == '_' {
+            literal.push(self.curr());
+            self.advance();
         }
 
-        self.src[self.pos]
+        Ok(())
     }
-    fn peek(&self) -> char {
-        if self.pos >= self.src.len() {
-            return 0 as char;
+
+    fn lex_radix_number(&mut self, location: Location) -> Result<Token, CompileError> {
+        let (radix, base) = match self.peek() {
+            'x' => (16, "hexadecimal"),
+            'o' => (8, "octal"),
+            _ => (2, "binary"),
+        };
+
+        let mut literal = String::new();
+        literal.push(self.curr());
+        self.advance();
+        literal.push(self.curr());
+        self.advance();
+
+        let mut invalid: Option<(char, Location)> = None;
+        let mut has_digits = false;
+
+        while self.curr().is_ascii_alphanumeric() || self.curr() == '_' {
+            let curr = self.curr();
+            if curr.is_digit(radix) {
+                has_digits = true;
+            } else if curr != '_' && invalid.is_none() {
+                invalid = Some((curr, self.location.clone()));
+            }
+
+            literal.push(curr);
+            self.advance();
+        }
+
+        if let Some((digit, at)) = invalid {
+            return Err(CompileError::lexical(
+                format!("invalid digit '{}' in {} literal", digit, base),
+                at,
+            ));
+        }
+
+        Self::check_underscores(&literal, 2, &location)?;
+
+        if !has_digits {
+            return Err(CompileError::lexical(
+                format!("missing digits in {} literal", base),
+                location,
+            ));
+        }
+
+        Ok(Token::new(
+            TokenKind::Int,
+            literal.into(),
+            location,
+            self.prev_location.clone(),
+        ))
+    }
+
+    /// Underscores may only separate two digits, so `1_000` is fine while
+    /// `0x_1`, `5_` and `1__0` are not. `skip` is the length of a radix prefix.
+    fn check_underscores(literal: &str, skip: usize, location: &Location) -> Result<(), CompileError> {
+        let chars: Vec<char> = literal.chars().collect();
+
+        for (i, ch) in chars.iter().enumerate().skip(skip) {
+            if *ch != '_' {
+                continue;
+            }
+
+            let is_digit = |c: Option<&char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+            if i > skip && is_digit(chars.get(i - 1)) && is_digit(chars.get(i + 1)) {
+                continue;
+            }
+
+            let mut at = location.clone();
+            for ch in &chars[..i] {
+                at.add_char(*ch);
+            }
+
+            return Err(CompileError::lexical(
+                format!("misplaced '_' in numeric literal '{}'", literal),
+                at,
+            ));
         }
 
-        self.src[self.pos + 1]
+        Ok(())
+    }
+
+    fn curr(&self) -> char {
+        self.peek_off(0)
+    }
+
+    fn peek(&self) -> char {
+        self.peek_off(1)
+    }
+
+    fn peek_off(&self, offset: usize) -> char {
+        self.src
+            .get(self.pos..)
+            .and_then(|rest| rest.chars().nth(offset))
+            .unwrap_or(0 as char)
     }
 
     fn advance(&mut self) {
-        if self.curr() == '\n' {
-            self.location.add_line();
-        } else {
-            self.location.add_col();
+        self.prev_location = self.location.clone();
+        match self.curr() {
+            // the `\n` of a `\r\n` pair is what moves to the next line
+            '\r' if self.peek() == '\n' => {}
+            '\n' | '\r' => self.location.add_line(),
+            '\t' => self.location.col += self.tab_width,
+            _ => self.location.add_col(),
         }
-        self.pos += 1;
+        self.pos += self.curr().len_utf8();
+        self.location.offset = self.pos;
+    }
+
+    fn is_line_break(c: char) -> bool {
+        c == '\n' || c == '\r'
     }
 
-    fn skip_comments(&mut self) {
+    fn skip_comments(&mut self) -> Result<(), CompileError> {
         while self.curr() == '/' && (self.peek() == '*' || self.peek() == '/') {
-            let curr = self.curr();
-            let peek = self.peek();
+            let location = self.location.clone();
+            let start = self.pos;
+            let is_block = self.peek() == '*';
             self.advance();
             self.advance();
 
-            if curr == '/' && peek == '*' {
-                while self.curr() != '*' && self.peek() != '/' && self.pos < self.src.len() {
+            if is_block {
+                loop {
+                    if self.pos >= self.src.len() {
+                        return Err(CompileError::lexical(
+                            "unterminated block comment".into(),
+                            location,
+                        ));
+                    }
+
+                    if self.curr() == '*' && self.peek() == '/' {
+                        self.advance();
+                        self.advance();
+                        break;
+                    }
+
                     self.advance();
                 }
-                self.advance();
-                self.advance();
             } else {
-                while self.curr() != '\n' && self.pos < self.src.len() {
+                while !Self::is_line_break(self.curr()) && self.pos < self.src.len() {
                     self.advance();
                 }
             }
 
+            if self.keep_comments {
+                self.comments.push(Token::new(
+                    TokenKind::Comment,
+                    self.src[start..self.pos].into(),
+                    location,
+                    self.prev_location.clone(),
+                ));
+            }
+
             self.skip_whitespaces();
         }
+
+        Ok(())
     }
 
     fn skip_whitespaces(&mut self) {
@@ -256,3 +726,412 @@ impl Lexer {
         }
     }
 }
+
+/// Token cursor returned by `Lexer::lex_all`.
+pub struct Tokens<'l, 'a> {
+    lexer: &'l mut Lexer<'a>,
+}
+
+impl Iterator for Tokens<'_, '_> {
+    type Item = Result<Token, CompileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lexer.next_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        let tokens = Lexer::from_str(src)
+            .lex()
+            .unwrap_or_else(|errs| panic!("{:?}", errs));
+        tokens.iter().map(|token| token.kind).collect()
+    }
+
+    fn errors(src: &str) -> Vec<String> {
+        match Lexer::from_str(src).lex() {
+            Ok(tokens) => panic!("expected a lexical error, got {:?}", tokens),
+            Err(errs) => errs.iter().map(|err| err.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn lexes_an_operator_at_the_end_of_input() {
+        assert_eq!(kinds("+"), [TokenKind::Plus, TokenKind::EOF]);
+        assert_eq!(kinds("x ="), [TokenKind::Id, TokenKind::Eq, TokenKind::EOF]);
+    }
+
+    #[test]
+    fn block_comments_end_only_at_star_slash() {
+        assert_eq!(kinds("/* a * b / c */ x"), [TokenKind::Id, TokenKind::EOF]);
+        assert_eq!(kinds("/***/ 1"), [TokenKind::Int, TokenKind::EOF]);
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_an_error() {
+        assert_eq!(
+            errors("x /* never closed"),
+            ["unterminated block comment at line: 1, col: 3"]
+        );
+    }
+
+    #[test]
+    fn tokens_span_their_whole_lexeme() {
+        let tokens = Lexer::from_str("hello").lex().unwrap();
+        assert_eq!((tokens[0].location.line, tokens[0].location.col), (1, 1));
+        assert_eq!((tokens[0].end.line, tokens[0].end.col), (1, 5));
+    }
+
+    #[test]
+    fn reports_every_bad_character() {
+        assert_eq!(
+            errors("a $\nb @"),
+            [
+                "unrecognized character '$' at line: 1, col: 3",
+                "unrecognized character '@' at line: 2, col: 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_char_literals() {
+        let tokens = Lexer::from_str(r"'x' '\n' '\''").lex().unwrap();
+        let literals: Vec<&str> = tokens.iter().map(|token| &*token.literal).collect();
+        assert_eq!(literals, ["'x'", r"'\n'", r"'\''", ""]);
+        assert!(
+            tokens[..3]
+                .iter()
+                .all(|token| token.kind == TokenKind::Char)
+        );
+    }
+
+    #[test]
+    fn empty_and_unterminated_char_literals_are_errors() {
+        assert_eq!(errors("''"), ["empty character literal at line: 1, col: 1"]);
+        assert_eq!(
+            errors("'a"),
+            ["unterminated character literal at line: 1, col: 1"]
+        );
+    }
+
+    #[test]
+    fn borrowed_and_owned_sources_lex_alike() {
+        let src =
+            "let naïve_ß = \"ünïcode\" + 'x';\nfn f(a: int) { return a * 31; }\n".repeat(2_000);
+        let summary = |tokens: Vec<Token>| -> Vec<(TokenKind, String, usize, usize, usize)> {
+            tokens
+                .into_iter()
+                .map(|token| {
+                    let at = token.location;
+                    (
+                        token.kind,
+                        token.literal.to_string(),
+                        at.line,
+                        at.col,
+                        at.offset,
+                    )
+                })
+                .collect()
+        };
+
+        let owned = summary(Lexer::new(src.clone()).lex().unwrap());
+        let borrowed = summary(Lexer::from_str(&src).lex().unwrap());
+
+        assert_eq!(owned.len(), 2_000 * 21 + 1);
+        assert_eq!(owned, borrowed);
+    }
+
+    #[test]
+    fn lexes_prefixed_integer_literals() {
+        let tokens = Lexer::from_str("0x1F 0o17 0b1010 0xDEADBEEF")
+            .lex()
+            .unwrap();
+        let values: Vec<(&str, i64)> = tokens[..4]
+            .iter()
+            .map(|token| (&*token.literal, token.int_value().unwrap()))
+            .collect();
+
+        assert_eq!(
+            values,
+            [
+                ("0x1F", 31),
+                ("0o17", 15),
+                ("0b1010", 10),
+                ("0xDEADBEEF", 0xDEADBEEF)
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_digit_for_the_base_is_an_error() {
+        assert_eq!(
+            errors("0x1G"),
+            ["invalid digit 'G' in hexadecimal literal at line: 1, col: 4"]
+        );
+        assert_eq!(
+            errors("0b102"),
+            ["invalid digit '2' in binary literal at line: 1, col: 5"]
+        );
+    }
+
+    #[test]
+    fn underscores_are_stripped_from_the_value() {
+        let tokens = Lexer::from_str("1_000_000").lex().unwrap();
+        assert_eq!(&*tokens[0].literal, "1_000_000");
+        assert_eq!(tokens[0].int_value(), Ok(1_000_000));
+    }
+
+    #[test]
+    fn misplaced_underscores_are_errors() {
+        assert_eq!(
+            errors("5_"),
+            ["misplaced '_' in numeric literal '5_' at line: 1, col: 2"]
+        );
+        assert_eq!(
+            errors("1__0"),
+            ["misplaced '_' in numeric literal '1__0' at line: 1, col: 2"]
+        );
+        // a leading underscore starts an identifier instead
+        assert_eq!(kinds("_5"), [TokenKind::Id, TokenKind::EOF]);
+    }
+
+    #[test]
+    fn rejects_extra_or_trailing_decimal_points() {
+        assert_eq!(
+            errors("1.2.3"),
+            ["unexpected second '.' in number '1.2.3' at line: 1, col: 4"]
+        );
+        assert_eq!(
+            errors("5."),
+            ["expected digits after '.' in number '5.' at line: 1, col: 2"]
+        );
+        assert_eq!(
+            kinds("3.14 0.0"),
+            [TokenKind::Float, TokenKind::Float, TokenKind::EOF]
+        );
+    }
+
+    #[test]
+    fn lexes_exponents_as_floats() {
+        let tokens = Lexer::from_str("1e10 2.5e-3 6.02E23").lex().unwrap();
+        let floats: Vec<(TokenKind, &str)> = tokens[..3]
+            .iter()
+            .map(|token| (token.kind, &*token.literal))
+            .collect();
+
+        assert_eq!(
+            floats,
+            [
+                (TokenKind::Float, "1e10"),
+                (TokenKind::Float, "2.5e-3"),
+                (TokenKind::Float, "6.02E23")
+            ]
+        );
+    }
+
+    #[test]
+    fn exponent_without_digits_is_an_error() {
+        assert_eq!(
+            errors("1e"),
+            ["missing exponent digits in number '1e' at line: 1, col: 3"]
+        );
+        assert_eq!(
+            errors("1e+"),
+            ["missing exponent digits in number '1e+' at line: 1, col: 4"]
+        );
+    }
+
+    #[test]
+    fn triple_quoted_strings_span_lines() {
+        let src = "\"\"\"one\ntwo\nthree\"\"\" x";
+        let tokens = Lexer::from_str(src).lex().unwrap();
+
+        assert_eq!(tokens[0].kind, TokenKind::String);
+        assert_eq!(tokens[0].unescaped(), Ok("one\ntwo\nthree".to_string()));
+        assert_eq!((tokens[1].location.line, tokens[1].location.col), (3, 10));
+    }
+
+    #[test]
+    fn plain_strings_reject_raw_newlines() {
+        assert_eq!(
+            errors("\"one\ntwo\""),
+            ["Unclosed string at line: 1, col: 1", "Unclosed string at line: 2, col: 4"]
+        );
+    }
+
+    #[test]
+    fn two_character_operators_are_one_token() {
+        assert_eq!(kinds("++"), [TokenKind::Inc, TokenKind::EOF]);
+        assert_eq!(kinds("--"), [TokenKind::Decr, TokenKind::EOF]);
+        assert_eq!(kinds("=="), [TokenKind::Eq2, TokenKind::EOF]);
+        assert_eq!(TokenKind::Decr.to_string(), "--");
+        assert_eq!(TokenKind::Inc.to_string(), "++");
+    }
+
+    #[test]
+    fn second_character_is_not_lexed_again() {
+        assert_eq!(
+            kinds("a++"),
+            [TokenKind::Id, TokenKind::Inc, TokenKind::EOF]
+        );
+        assert_eq!(
+            kinds("x == y"),
+            [TokenKind::Id, TokenKind::Eq2, TokenKind::Id, TokenKind::EOF]
+        );
+    }
+
+    #[test]
+    fn dot_inside_a_number_is_a_decimal_point() {
+        assert_eq!(kinds("1.5"), [TokenKind::Float, TokenKind::EOF]);
+        assert_eq!(
+            kinds("a.b"),
+            [TokenKind::Id, TokenKind::Dot, TokenKind::Id, TokenKind::EOF]
+        );
+    }
+
+    #[test]
+    fn collects_comments_on_request() {
+        let src = "// first\nlet x = 1; /* second */";
+        let mut lexer = Lexer::from_str(src).with_comments();
+        let tokens = lexer.lex().unwrap();
+
+        assert!(tokens.iter().all(|token| token.kind != TokenKind::Comment));
+        let comments: Vec<(&str, usize, usize)> = lexer
+            .comments()
+            .iter()
+            .map(|token| (&*token.literal, token.location.line, token.location.col))
+            .collect();
+        assert_eq!(comments, [("// first", 1, 1), ("/* second */", 2, 12)]);
+    }
+
+    #[test]
+    fn drops_comments_by_default() {
+        let mut lexer = Lexer::from_str("// first\n1");
+        lexer.lex().unwrap();
+        assert!(lexer.comments().is_empty());
+    }
+
+    #[test]
+    fn lexes_unicode_identifiers() {
+        let tokens = Lexer::from_str("café _x1").lex().unwrap();
+        let ids: Vec<(TokenKind, &str)> = tokens[..2]
+            .iter()
+            .map(|token| (token.kind, &*token.literal))
+            .collect();
+        assert_eq!(ids, [(TokenKind::Id, "café"), (TokenKind::Id, "_x1")]);
+    }
+
+    #[test]
+    fn rejects_identifiers_starting_with_emoji_or_digit() {
+        assert_eq!(
+            errors("🙂x"),
+            ["unrecognized character '🙂' at line: 1, col: 1"]
+        );
+        assert_eq!(
+            errors("1abc"),
+            ["identifiers cannot start with a digit: '1abc' at line: 1, col: 1"]
+        );
+    }
+
+    #[test]
+    fn carriage_returns_end_lines() {
+        for src in ["a\r\nb", "a\rb"] {
+            let tokens = Lexer::from_str(src).lex().unwrap();
+            assert_eq!((tokens[1].location.line, tokens[1].location.col), (2, 1));
+        }
+    }
+
+    #[test]
+    fn every_keyword_lexes_to_its_kind() {
+        for (word, kind) in TokenKind::keywords() {
+            assert!(TokenKind::is_keyword(word));
+            assert_eq!(kinds(word), [*kind, TokenKind::EOF], "keyword {}", word);
+        }
+        assert!(!TokenKind::is_keyword("main"));
+    }
+
+    #[test]
+    fn offsets_count_bytes() {
+        let tokens = Lexer::from_str("é = \"ü\"; x").lex().unwrap();
+        let offsets: Vec<usize> = tokens.iter().map(|token| token.location.offset).collect();
+        assert_eq!(offsets, [0, 3, 5, 9, 11, 12]);
+    }
+
+    #[test]
+    fn multi_character_tokens_end_at_their_last_byte() {
+        let tokens = Lexer::from_str("a == b").lex().unwrap();
+        assert_eq!((tokens[1].end.col, tokens[1].end.offset), (4, 3));
+        assert_eq!(tokens[2].location.offset, 5);
+    }
+
+    #[test]
+    fn tabs_advance_by_the_tab_width() {
+        let tokens = Lexer::from_str("\tx\n\t\ty")
+            .with_tab_width(4)
+            .lex()
+            .unwrap();
+        assert_eq!(tokens[0].location.col, 5);
+        assert_eq!(tokens[1].location.col, 9);
+
+        let tokens = Lexer::from_str("\tx").lex().unwrap();
+        assert_eq!(tokens[0].location.col, 2);
+    }
+
+    #[test]
+    fn first_token_is_at_line_one_col_one() {
+        let tokens = Lexer::from_str("first second").lex().unwrap();
+        assert_eq!(tokens[0].location.to_string(), "line: 1, col: 1");
+        assert_eq!(tokens[1].location.to_string(), "line: 1, col: 7");
+    }
+
+    #[test]
+    fn lex_all_yields_what_lex_collects() {
+        let src = "fn f(a: int) -> int { return a + 1; } // done";
+        let collected = Lexer::from_str(src).lex().unwrap();
+        let mut lexer = Lexer::from_str(src);
+        let streamed: Vec<Token> = lexer.lex_all().map(Result::unwrap).collect();
+
+        let summary = |tokens: &[Token]| -> Vec<(TokenKind, String, usize)> {
+            tokens
+                .iter()
+                .map(|token| (token.kind, token.literal.to_string(), token.location.col))
+                .collect()
+        };
+        assert_eq!(summary(&streamed), summary(&collected));
+        assert_eq!(streamed.last().unwrap().kind, TokenKind::EOF);
+    }
+
+    #[test]
+    fn lex_all_keeps_going_after_an_error() {
+        let mut lexer = Lexer::from_str("a $ b");
+        let results: Vec<Result<TokenKind, String>> = lexer
+            .lex_all()
+            .map(|result| {
+                result
+                    .map(|token| token.kind)
+                    .map_err(|err| err.to_string())
+            })
+            .collect();
+
+        assert_eq!(
+            results,
+            [
+                Ok(TokenKind::Id),
+                Err("unrecognized character '$' at line: 1, col: 3".to_string()),
+                Ok(TokenKind::Id),
+                Ok(TokenKind::EOF),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_identifiers_share_one_symbol() {
+        let tokens = Lexer::from_str("foo + foo * bar").lex().unwrap();
+        assert!(Rc::ptr_eq(&tokens[0].literal, &tokens[2].literal));
+        assert!(!Rc::ptr_eq(&tokens[0].literal, &tokens[4].literal));
+        assert_eq!(&*tokens[2].literal, "foo");
+    }
+}
@@ -1,5 +1,14 @@
 pub mod tokens;
+pub mod error;
 pub mod lexer;
 pub mod ast;
+pub mod env;
+pub mod args;
 pub mod visitors;
 pub mod parser;
+pub mod interpreter;
+pub mod typechecker;
+pub mod formatter;
+pub mod bytecode;
+
+pub use error::render_error;
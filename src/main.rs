@@ -1,43 +1,455 @@
 use colored::*;
 
-use std::{env, fs::File, io::Read, process};
+use std::{
+    collections::HashSet,
+    env,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+};
 
-use ice::{lexer::Lexer, parser};
+use ice::{
+    ast::{Expression, Statement},
+    error::{CompileError, ErrorKind},
+    interpreter::{Interpreter, Value},
+    lexer::Lexer,
+    parser, render_error,
+    tokens::{Token, TokenKind},
+    typechecker::TypeChecker,
+};
+
+enum Mode {
+    Dump,
+    Tokens,
+    AstJson,
+    Eval,
+    Check,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("{}", "failed to compile 'ice' program".red().bold());
-        eprintln!("{}", "usage:".bright_blue());
-        eprintln!("\t{} {}", args[0].green(), "<your-file.ic>".blue().bold());
-        process::exit(1);
+    if args.len() == 1 {
+        run_repl();
+        return;
+    }
+
+    let mut mode = Mode::Dump;
+    let mut file: Option<&String> = None;
+
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--tokens" => mode = Mode::Tokens,
+            "--ast-json" => mode = Mode::AstJson,
+            "--eval" => mode = Mode::Eval,
+            "--check" => mode = Mode::Check,
+            "--help" => {
+                println!("{}", usage_text(&args[0]));
+                return;
+            }
+            "--version" => {
+                println!("ice {}", env!("CARGO_PKG_VERSION"));
+                return;
+            }
+            flag if flag.starts_with("--") => usage(&args[0]),
+            _ if file.is_none() => file = Some(arg),
+            _ => usage(&args[0]),
+        }
+    }
+
+    let Some(file) = file else {
+        usage(&args[0]);
+    };
+
+    let src = read_source(file);
+    let path = if file == "-" { "<stdin>" } else { file };
+
+    if let Mode::Tokens = mode {
+        dump_tokens(path, &src);
+        return;
+    }
+
+    if let Mode::Check = mode {
+        check_program(file, path, &src);
+        return;
+    }
+
+    let tree = parse_source(path, &src);
+    let tree: Vec<Statement> = resolve_main_imports(file, &src, tree)
+        .into_iter()
+        .flat_map(|unit| unit.stmts)
+        .collect();
+
+    if let Mode::AstJson = mode {
+        let json = serde_json::to_string_pretty(&tree)
+            .unwrap_or_else(|err| panic!("{}: {}", "failed to serialize the tree".red(), err));
+        println!("{}", json);
+        return;
+    }
+
+    if let Mode::Eval = mode {
+        eval_program(&tree);
+        return;
     }
 
-    let mut file = File::open(args[1].clone()).expect(
-        format!(
-            "{}: {}",
-            "failed to open file".red().bold(),
-            args[1].green(),
-        )
-        .as_str(),
+    for stmt in tree {
+        println!("stmt: {}", stmt);
+    }
+}
+
+/// Lexes and parses a whole file, reporting any errors and exiting.
+fn parse_source(path: &str, src: &str) -> Vec<Statement> {
+    let tokens = match Lexer::from_str(src).lex() {
+        Ok(tokens) => tokens,
+        Err(errs) => {
+            for err in errs {
+                report(path, src, &err);
+            }
+            process::exit(1);
+        }
+    };
+
+    match parser::Parser::new(tokens).parse() {
+        Ok(tree) => tree,
+        Err(errs) => {
+            for err in errs {
+                report(path, src, &err);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn resolve_main_imports(file: &str, src: &str, tree: Vec<Statement>) -> Vec<Unit> {
+    let path = if file == "-" { "<stdin>" } else { file };
+    let mut imports = Imports::default();
+    if let Ok(canonical) = fs::canonicalize(file) {
+        imports.loaded.insert(canonical.clone());
+        imports.chain.push((canonical, path.to_string()));
+    }
+
+    resolve_imports(file, src.into(), tree, &mut imports)
+}
+
+/// Top-level statements from one file, in program order. The imports in a
+/// file split it into several units around the files they splice in.
+struct Unit {
+    path: Rc<str>,
+    src: Rc<str>,
+    stmts: Vec<Statement>,
+}
+
+/// Files spliced in so far, and the chain of imports currently being
+/// resolved, which tells a file imported twice apart from a cycle.
+#[derive(Default)]
+struct Imports {
+    loaded: HashSet<PathBuf>,
+    chain: Vec<(PathBuf, String)>,
+}
+
+/// Replaces every top-level `import` with the statements of the imported
+/// file, resolved relative to the importing one. Each file is spliced in
+/// only once, however many files import it.
+fn resolve_imports(
+    file: &str,
+    src: Rc<str>,
+    tree: Vec<Statement>,
+    imports: &mut Imports,
+) -> Vec<Unit> {
+    let path: Rc<str> = if file == "-" { "<stdin>" } else { file }.into();
+    let dir = match Path::new(file).parent() {
+        Some(dir) if file != "-" => dir,
+        _ => Path::new(""),
+    };
+    let mut units: Vec<Unit> = Vec::new();
+    let mut stmts: Vec<Statement> = Vec::new();
+
+    for stmt in tree {
+        let Statement::Import { path: import, .. } = &stmt else {
+            stmts.push(stmt);
+            continue;
+        };
+
+        let relative = import
+            .unescaped()
+            .unwrap_or_else(|err| import_error(&path, &src, import, err));
+        let target = dir.join(relative).to_string_lossy().to_string();
+        let Ok(canonical) = fs::canonicalize(&target) else {
+            let message = format!("cannot find '{}'", target);
+            import_error(&path, &src, import, message);
+        };
+
+        if let Some(start) = imports.chain.iter().position(|(p, _)| *p == canonical) {
+            let cycle: Vec<&str> = imports.chain[start..]
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .chain([target.as_str()])
+                .collect();
+            let message = format!("circular import {}", cycle.join(" -> "));
+            import_error(&path, &src, import, message);
+        }
+
+        if !imports.loaded.insert(canonical.clone()) {
+            continue;
+        }
+
+        let imported_src = read_source(&target);
+        let imported = parse_source(&target, &imported_src);
+
+        if !stmts.is_empty() {
+            units.push(Unit {
+                path: path.clone(),
+                src: src.clone(),
+                stmts: std::mem::take(&mut stmts),
+            });
+        }
+
+        imports.chain.push((canonical, target.clone()));
+        units.extend(resolve_imports(
+            &target,
+            imported_src.into(),
+            imported,
+            imports,
+        ));
+        imports.chain.pop();
+    }
+
+    if !stmts.is_empty() {
+        units.push(Unit { path, src, stmts });
+    }
+
+    units
+}
+
+fn import_error(path: &str, src: &str, import: &Token, message: String) -> ! {
+    let err = CompileError::import(message, import.location.clone());
+    report(path, src, &err);
+    process::exit(1);
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!("{}", "failed to compile 'ice' program".red().bold());
+    eprintln!("{}", usage_text(program));
+    process::exit(1);
+}
+
+fn usage_text(program: &str) -> String {
+    let flags = [
+        ("--tokens", "print the tokens of the file"),
+        ("--ast-json", "print the syntax tree as JSON"),
+        ("--eval", "run the program"),
+        ("--check", "typecheck the program"),
+        ("--help", "print this message"),
+        ("--version", "print the version"),
+    ];
+
+    let mut text = format!(
+        "{}\n\t{} {} {}\n\t{} {}\n\n{}",
+        "usage:".bright_blue(),
+        program.green(),
+        "[--tokens | --ast-json | --eval | --check]".blue(),
+        "<your-file.ic | ->".blue().bold(),
+        program.green(),
+        "(with no arguments, starts a repl)".dimmed(),
+        "flags:".bright_blue(),
     );
+    for (flag, description) in flags {
+        text.push_str(&format!("\n\t{:<12}{}", flag.blue(), description));
+    }
+    text
+}
+
+fn dump_tokens(path: &str, src: &str) {
+    match Lexer::from_str(src).lex() {
+        Ok(tokens) => {
+            for token in tokens {
+                println!("{:?} {:?} at {}", token.kind, token.literal, token.location);
+            }
+        }
+        Err(errs) => {
+            for err in errs {
+                report(path, src, &err);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn eval_program(tree: &[Statement]) {
+    let mut interpreter = Interpreter::new();
+
+    for stmt in tree {
+        // a top-level function literal is a declaration, not a value to show
+        let is_declaration = matches!(
+            stmt,
+            Statement::ExpressionStatement {
+                expression: Expression::FunctionLiteral { .. },
+                ..
+            }
+        );
+
+        match interpreter.run_stmt(stmt) {
+            Ok(Some(_)) if is_declaration => {}
+            Ok(Some(Value::Unit)) | Ok(None) => {}
+            Ok(Some(value)) => println!("{}", value),
+            Err(err) => {
+                eprintln!("{}: {}", "runtime error".red().bold(), err);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// Reports every lexical, syntax and type error of the program at once,
+/// file by file in source order. Statements that failed to parse are left
+/// out of the type check.
+fn check_program(file: &str, path: &str, src: &str) {
+    let mut lexer = Lexer::from_str(src);
+    let (tree, errs) = parser::Parser::from_lexer(lexer.lex_all()).parse_all();
+    let units = resolve_main_imports(file, src, tree);
+
+    // errors grouped by file, the main file first
+    let mut files: Vec<(Rc<str>, Rc<str>, Vec<CompileError>)> =
+        vec![(path.into(), src.into(), errs)];
+    let mut checker = TypeChecker::new();
+    let mut warned = 0;
+
+    for unit in units {
+        let result = checker.check(&unit.stmts);
+
+        for warning in &checker.warnings()[warned..] {
+            // only warnings from imported files need to say where they are
+            if &*unit.path == path {
+                eprintln!("{}: {}", "warning".yellow().bold(), warning);
+            } else {
+                eprintln!(
+                    "{} {}: {}",
+                    "warning".yellow().bold(),
+                    format!("in {}", unit.path).green(),
+                    warning
+                );
+            }
+        }
+        warned = checker.warnings().len();
 
+        let Err(errs) = result else {
+            continue;
+        };
+        match files.iter_mut().find(|(path, ..)| *path == unit.path) {
+            Some((.., file_errs)) => file_errs.extend(errs),
+            None => files.push((unit.path, unit.src, errs)),
+        }
+    }
+
+    let mut failed = false;
+    for (path, src, mut errs) in files {
+        errs.sort_by_key(|err| (err.location.line, err.location.col));
+        for err in &errs {
+            report(&path, &src, err);
+        }
+        failed |= !errs.is_empty();
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn read_source(path: &str) -> String {
     let mut src = String::new();
+
+    if path == "-" {
+        io::stdin()
+            .read_to_string(&mut src)
+            .unwrap_or_else(|_| panic!("{}", "failed to read from stdin".red()));
+        return src;
+    }
+
+    let mut file = File::open(path)
+        .unwrap_or_else(|_| panic!("{}: {}", "failed to open file".red().bold(), path.green()));
+
     file.read_to_string(&mut src)
-        .expect(format!("{}", "failed to read file contents".red(),).as_str());
+        .unwrap_or_else(|_| panic!("{}", "failed to read file contents".red()));
 
-    let mut lexer = Lexer::new(src.into());
-    match lexer.lex() {
-        Ok(tokens) => {
-            let mut parser = parser::Parser::new(tokens);
-            let tree = parser.parse().unwrap();
-            for stmt in tree {
-                println!("stmt: {}", stmt);
+    src
+}
+
+fn run_repl() {
+    let stdin = io::stdin();
+    let mut src = String::new();
+
+    loop {
+        print!("{} ", if src.is_empty() { ">>" } else { ".." });
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                println!();
+                break;
             }
+            Ok(_) => src.push_str(&line),
         }
-        Err(err) => {
-            eprintln!("{}: {}", "lexical error".red().bold(), err.bright_red());
-            process::exit(1);
+
+        let tokens = match Lexer::new(src.clone()).lex() {
+            Ok(tokens) => tokens,
+            Err(errs) => {
+                for err in errs {
+                    report("<repl>", &src, &err);
+                }
+                src.clear();
+                continue;
+            }
+        };
+
+        if is_incomplete(&tokens) {
+            continue;
         }
+
+        match parser::Parser::new(tokens).parse() {
+            Ok(tree) => {
+                for stmt in tree {
+                    println!("stmt: {}", stmt);
+                }
+            }
+            Err(errs) => {
+                for err in errs {
+                    report("<repl>", &src, &err);
+                }
+            }
+        }
+
+        src.clear();
     }
 }
+
+fn is_incomplete(tokens: &[Token]) -> bool {
+    let mut depth: isize = 0;
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::LParen | TokenKind::LCurly | TokenKind::LBracket => depth += 1,
+            TokenKind::RParen | TokenKind::RCurly | TokenKind::RBracket => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+fn report(path: &str, src: &str, err: &CompileError) {
+    let label = match err.kind {
+        ErrorKind::Lexical => "lexical error",
+        ErrorKind::Syntax => "syntax error",
+        ErrorKind::Type => "type error",
+        ErrorKind::Import => "import error",
+    };
+
+    eprintln!(
+        "{} {}: {}",
+        label.red().bold(),
+        format!("in {}:{:#}", path, err.location).green(),
+        render_error(src, &err.location, &err.message.bright_red().to_string())
+    );
+}
@@ -1,22 +1,128 @@
+use std::collections::HashSet;
+
 use crate::ast::{Expression, Statement, Type, TypeMapping};
-use crate::tokens::{Token, TokenKind};
+use crate::error::CompileError;
+use crate::tokens::{Location, Span, Token, TokenKind};
+
+/// A single argument of a call, before positional and named ones are split.
+enum Argument {
+    Positional(Expression),
+    Named(Token, Expression),
+}
 
-pub struct Parser {
+/// Positional and named arguments of a call.
+type CallArgs = (Vec<Expression>, Vec<(Token, Expression)>);
+
+/// How many tokens past the current one the parser may look at.
+const LOOKAHEAD: usize = 1;
+
+/// Every binary operator with its left and right binding power. A higher
+/// power binds tighter, and a right power lower than the left one makes the
+/// operator right-associative. New operators only need an entry here.
+const BINARY_OPERATORS: &[(TokenKind, (usize, usize))] = &[
+    (TokenKind::PipePipe, (1, 2)),
+    (TokenKind::AmpAmp, (3, 4)),
+    (TokenKind::Eq2, (5, 6)),
+    (TokenKind::Ne, (5, 6)),
+    (TokenKind::Lt, (5, 6)),
+    (TokenKind::Gt, (5, 6)),
+    (TokenKind::Le, (5, 6)),
+    (TokenKind::Ge, (5, 6)),
+    (TokenKind::Plus, (7, 8)),
+    (TokenKind::Minus, (7, 8)),
+    (TokenKind::Star, (9, 10)),
+    (TokenKind::Slash, (9, 10)),
+    (TokenKind::Percent, (9, 10)),
+    (TokenKind::Caret, (12, 11)),
+];
+
+/// Default limit on how deeply expressions may nest before the parser gives
+/// up, well before the recursion could overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+pub struct Parser<'a> {
     tokens: Vec<Token>,
+    /// Tokens not pulled into `tokens` yet, when parsing from an iterator.
+    source: Box<dyn Iterator<Item = Result<Token, CompileError>> + 'a>,
+    /// Errors the source yielded in place of tokens, reported by `parse`.
+    lex_errors: Vec<CompileError>,
     pos: usize,
+    /// How many function literal bodies enclose the current token.
+    function_depth: usize,
+    /// How many blocks of any kind enclose the current token.
+    block_depth: usize,
+    /// How many nested expressions the parser is currently inside.
+    depth: usize,
+    max_depth: usize,
 }
 
-impl Parser {
+impl<'a> Parser<'a> {
     pub fn new(tokens: Vec<Token>) -> Self {
         Self {
-            tokens: tokens,
+            tokens,
+            source: Box::new(std::iter::empty()),
+            lex_errors: Vec::new(),
+            pos: 0,
+            function_depth: 0,
+            block_depth: 0,
+            depth: 0,
+            max_depth: MAX_DEPTH,
+        }
+    }
+
+    /// Parses tokens pulled from `tokens` as they are needed, instead of
+    /// requiring the whole stream up front.
+    pub fn from_tokens<I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = Token>,
+        I::IntoIter: 'a,
+    {
+        Self::from_lexer(tokens.into_iter().map(Ok))
+    }
+
+    /// Parses straight from `Lexer::lex_all`. Lexical errors are collected
+    /// and returned by `parse` together with the syntax errors, in source
+    /// order.
+    pub fn from_lexer<I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = Result<Token, CompileError>>,
+        I::IntoIter: 'a,
+    {
+        let mut parser = Self {
+            tokens: Vec::new(),
+            source: Box::new(tokens.into_iter()),
+            lex_errors: Vec::new(),
             pos: 0,
+            function_depth: 0,
+            block_depth: 0,
+            depth: 0,
+            max_depth: MAX_DEPTH,
+        };
+        parser.fill();
+
+        parser
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Statement>, Vec<CompileError>> {
+        let (stmts, errs) = self.parse_all();
+
+        if !errs.is_empty() {
+            return Err(errs);
         }
+
+        Ok(stmts)
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>, Vec<String>> {
+    /// Like `parse`, but keeps the statements that did parse alongside the
+    /// errors, for reporting later diagnostics about them too.
+    pub fn parse_all(&mut self) -> (Vec<Statement>, Vec<CompileError>) {
         let mut stmts: Vec<Statement> = Vec::new();
-        let mut errs: Vec<String> = Vec::new();
+        let mut errs: Vec<CompileError> = Vec::new();
 
         loop {
             match self.parse_stmt() {
@@ -30,236 +136,365 @@ impl Parser {
                 Err(err) => {
                     errs.push(err);
                     self.advance();
+
+                    if self.pos >= self.tokens.len() {
+                        break;
+                    }
                 }
             }
         }
 
-        if errs.len() != 0 {
-            return Err(errs);
+        if !self.lex_errors.is_empty() {
+            errs.append(&mut self.lex_errors);
+            errs.sort_by_key(|err| (err.location.line, err.location.col));
         }
 
-        Ok(stmts)
+        (stmts, errs)
     }
 
-    fn parse_stmt(&mut self) -> Result<Statement, String> {
+    fn parse_stmt(&mut self) -> Result<Statement, CompileError> {
         if let Some(curr) = self.curr() {
+            let start = curr.location.clone();
             match curr.kind {
                 TokenKind::EOF => return Ok(Statement::Halt),
+                TokenKind::Semicolon => {
+                    self.advance();
+                    return Ok(Statement::Empty {
+                        span: self.span_from(&start),
+                    });
+                }
                 TokenKind::Return => {
+                    if self.function_depth == 0 {
+                        return Err(CompileError::syntax(
+                            "return outside of function".into(),
+                            start,
+                        ));
+                    }
+
                     self.advance();
-                    if let Ok(_) = self.expect(TokenKind::Semicolon) {
+                    if self.curr_is(TokenKind::Semicolon) {
                         self.advance();
-                        return Ok(Statement::Return { value: None });
+                        return Ok(Statement::Return {
+                            value: None,
+                            span: self.span_from(&start),
+                        });
                     }
 
                     let expr = self.parse_expr()?;
 
                     self.expect(TokenKind::Semicolon)?;
                     self.advance();
-                    return Ok(Statement::Return { value: Some(expr) });
+                    return Ok(Statement::Return {
+                        value: Some(expr),
+                        span: self.span_from(&start),
+                    });
+                }
+                TokenKind::Let | TokenKind::Const => {
+                    return self.parse_let();
+                }
+                TokenKind::If => {
+                    return self.parse_if();
+                }
+                TokenKind::While => {
+                    return self.parse_while(None);
+                }
+                TokenKind::For => {
+                    return self.parse_for(None);
+                }
+                // `name:` cannot start an expression, so it must be a label
+                TokenKind::Id if self.peek_is(TokenKind::Colon) => {
+                    return self.parse_labeled_loop();
+                }
+                TokenKind::Break | TokenKind::Continue => {
+                    let kind = curr.kind;
+                    self.advance();
+
+                    let mut label: Option<Token> = None;
+                    if let Ok(id) = self.curr_expect(TokenKind::Id) {
+                        label = Some(id.clone());
+                        self.advance();
+                    }
+
+                    self.expect(TokenKind::Semicolon)?;
+                    self.advance();
+
+                    let span = self.span_from(&start);
+                    if kind.is(TokenKind::Break) {
+                        return Ok(Statement::Break { label, span });
+                    }
+                    return Ok(Statement::Continue { label, span });
+                }
+                TokenKind::Struct => {
+                    return self.parse_struct_decl();
+                }
+                TokenKind::Import => {
+                    return self.parse_import();
                 }
                 _ => {
                     let expr = self.parse_expr()?;
 
-                    match expr {
-                        Expression::FunctionLiteral { .. } => {}
-                        _ => {
-                            self.expect(TokenKind::Semicolon)?;
-                            self.advance();
-                        }
+                    if !expr.ends_with_block() {
+                        self.expect(TokenKind::Semicolon)?;
+                        self.advance();
                     }
 
-                    return Ok(Statement::ExpressionStatement { expression: expr });
+                    return Ok(Statement::ExpressionStatement {
+                        expression: expr,
+                        span: self.span_from(&start),
+                    });
                 }
             }
         }
 
-        Err("expected statement before the end of input".into())
+        Err(CompileError::syntax(
+            "expected statement before the end of input".into(),
+            self.last_location(),
+        ))
     }
 
-    fn parse_expr(&mut self) -> Result<Expression, String> {
-        self.parse_binary(0)
-    }
+    fn parse_let(&mut self) -> Result<Statement, CompileError> {
+        let let_keyword = match self.curr() {
+            Some(curr) if curr.kind.is(TokenKind::Const) => curr.clone(),
+            _ => self.curr_expect(TokenKind::Let)?.clone(),
+        };
+        self.advance();
 
-    fn parse_binary(&mut self, min_bp: usize) -> Result<Expression, String> {
-        let mut lhs = self.parse_prefix()?;
+        let name = match self.curr() {
+            Some(curr) if curr.kind.is(TokenKind::Id) => curr.clone(),
+            Some(curr) if TokenKind::is_keyword(&curr.literal) => {
+                return Err(Self::keyword_as_name(curr));
+            }
+            Some(curr) => {
+                return Err(CompileError::syntax(
+                    format!(
+                        "expected variable name after '{}', but got {}",
+                        let_keyword.kind, curr.kind
+                    ),
+                    curr.location.clone(),
+                ));
+            }
+            None => {
+                return Err(CompileError::syntax(
+                    format!("expected variable name after '{}'", let_keyword.kind),
+                    let_keyword.location.clone(),
+                ));
+            }
+        };
+        self.advance();
 
-        while let Some(op) = self.curr() {
-            if Self::is_binary_operator(op.kind) {
-                let op = op.clone();
-                let (l_bp, r_bp) = Self::get_binding_power(op.kind);
+        let mut type_annotation: Option<Type> = None;
+        if self.curr_is(TokenKind::Colon) {
+            self.advance();
+            type_annotation = Some(self.parse_type()?);
+        }
 
-                if l_bp < min_bp {
-                    break;
-                }
+        self.expect(TokenKind::Eq)?;
+        self.advance();
 
-                self.advance();
+        let value = self.parse_expr()?;
 
-                let rhs = self.parse_binary(r_bp)?;
+        self.expect(TokenKind::Semicolon)?;
+        self.advance();
 
-                lhs = Expression::Binary {
-                    lhs: lhs.into(),
-                    op: op.kind,
-                    rhs: rhs.into(),
-                }
-            } else {
-                break;
-            }
+        let span = self.span_from(&let_keyword.location);
+
+        if let_keyword.kind.is(TokenKind::Const) {
+            return Ok(Statement::Const {
+                name,
+                type_annotation,
+                value,
+                span,
+            });
         }
 
-        return Ok(lhs);
+        Ok(Statement::Let {
+            name,
+            type_annotation,
+            value,
+            span,
+        })
     }
 
-    fn parse_prefix(&mut self) -> Result<Expression, String> {
-        if let Some(curr) = self.curr() {
-            let curr = curr.clone();
-            match curr.kind {
-                TokenKind::Plus | TokenKind::Minus => {
-                    self.advance();
-                    return Ok(Expression::Unary {
-                        op: curr.kind,
-                        expr: self.parse_atom()?.into(),
-                    });
-                }
-                _ => {
-                    return self.parse_atom();
-                }
+    fn parse_if(&mut self) -> Result<Statement, CompileError> {
+        let start = self.curr_expect(TokenKind::If)?.location.clone();
+        self.advance();
+
+        self.expect(TokenKind::LParen)?;
+        self.advance();
+        let condition = self.parse_expr()?;
+        self.expect(TokenKind::RParen)?;
+        self.advance();
+
+        let then_branch = self.parse_block()?;
+
+        let mut else_branch: Option<Vec<Statement>> = None;
+        if self.curr_is(TokenKind::Else) {
+            self.advance();
+
+            // `else if` is an else branch holding a single nested if
+            if self.curr_is(TokenKind::If) {
+                else_branch = Some(vec![self.nested(Self::parse_if)?]);
+            } else {
+                else_branch = Some(self.parse_block()?);
             }
         }
 
-        Err("".into())
+        Ok(Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            span: self.span_from(&start),
+        })
     }
 
-    fn parse_atom(&mut self) -> Result<Expression, String> {
-        if let Some(curr) = self.curr() {
-            let curr = curr.clone();
-            match curr.kind {
-                TokenKind::Fn => {
-                    return self.parse_function_literal_or_call();
-                }
-                TokenKind::Id => {
-                    return self.parse_id_or_function_call();
-                }
-                TokenKind::Int => {
-                    self.advance();
-                    return Ok(Expression::Int { value: curr });
-                }
-                _ => {
-                    return Err(format!(
-                        "unexpected token '{}' ({:?}) at {}",
-                        curr.literal, curr.kind, curr.location
-                    ));
-                }
-            }
+    fn parse_labeled_loop(&mut self) -> Result<Statement, CompileError> {
+        let label = self.curr_expect(TokenKind::Id)?.clone();
+        self.advance();
+        self.expect(TokenKind::Colon)?;
+        self.advance();
+
+        if self.curr_is(TokenKind::While) {
+            return self.parse_while(Some(label));
+        }
+        if self.curr_is(TokenKind::For) {
+            return self.parse_for(Some(label));
         }
 
-        Err("unexpected end of input while parsing expression".into())
+        Err(CompileError::syntax(
+            format!("label '{}' must be followed by a loop", label.literal),
+            label.location.clone(),
+        ))
     }
 
-    fn parse_id_or_function_call(&mut self) -> Result<Expression, String> {
-        self.expect(TokenKind::Id)?;
+    fn parse_while(&mut self, label: Option<Token>) -> Result<Statement, CompileError> {
+        let keyword = self.curr_expect(TokenKind::While)?.location.clone();
+        let start = label
+            .as_ref()
+            .map_or(keyword, |label| label.location.clone());
+        self.advance();
 
-        if matches!(self.peek(), Some(peek) if peek.kind.is(TokenKind::LParen)) {
-            return self.parse_function_call();
-        }
+        self.expect(TokenKind::LParen)?;
+        self.advance();
+        let condition = self.parse_expr()?;
+        self.expect(TokenKind::RParen)?;
+        self.advance();
 
-        if let Some(curr) = self.curr().cloned() {
-            self.advance();
-            return Ok(Expression::Id { name: curr.clone() });
-        }
+        let body = self.parse_block()?;
 
-        Err("".into())
+        Ok(Statement::While {
+            label,
+            condition,
+            body,
+            span: self.span_from(&start),
+        })
     }
 
-    fn parse_function_call(&mut self) -> Result<Expression, String> {
-        self.expect(TokenKind::Id)?;
-        if let Some(curr) = self.curr() {
-            let name = curr.clone();
+    fn parse_for(&mut self, label: Option<Token>) -> Result<Statement, CompileError> {
+        let keyword = self.curr_expect(TokenKind::For)?.location.clone();
+        let start = label
+            .as_ref()
+            .map_or(keyword, |label| label.location.clone());
+        self.advance();
 
-            self.advance();
-            self.expect(TokenKind::LParen)?;
-            self.advance();
+        self.expect(TokenKind::LParen)?;
+        self.advance();
 
-            let mut args: Vec<Expression> = Vec::new();
+        // the init clause is a full statement and consumes its own `;`
+        let init = if self.curr_is(TokenKind::Semicolon) {
+            self.advance();
+            None
+        } else {
+            Some(Box::new(self.parse_stmt()?))
+        };
+
+        let condition = if self.curr_is(TokenKind::Semicolon) {
+            None
+        } else {
+            Some(self.parse_expr()?)
+        };
+        self.expect(TokenKind::Semicolon)?;
+        self.advance();
 
-            while let Some(curr) = self.curr() {
-                match curr.kind {
-                    TokenKind::RParen => {
-                        self.advance();
-                        break;
-                    }
-                    _ => {
-                        args.push(self.parse_expr()?);
-                        if let Ok(_) = self.expect(TokenKind::Comma) {
-                            self.advance();
-                        }
-                    }
-                }
-            }
+        let step = if self.curr_is(TokenKind::RParen) {
+            None
+        } else {
+            Some(self.parse_expr()?)
+        };
+        self.expect(TokenKind::RParen)?;
+        self.advance();
 
-            return Ok(Expression::FunctionCall {
-                callee: Box::from(Expression::Id { name }),
-                args,
-            });
-        }
+        let body = self.parse_block()?;
 
-        Err("expected identifier before function call".into())
+        Ok(Statement::For {
+            label,
+            init,
+            condition,
+            step,
+            body,
+            span: self.span_from(&start),
+        })
     }
 
-    fn parse_function_literal_or_call(&mut self) -> Result<Expression, String> {
-        let fn_keyword = self.curr_expect(TokenKind::Fn)?.clone();
+    fn parse_struct_decl(&mut self) -> Result<Statement, CompileError> {
+        let start = self.curr_expect(TokenKind::Struct)?.location.clone();
         self.advance();
 
-        let mut name: Option<Token> = None;
-
-        if let Ok(id) = self.curr_expect(TokenKind::Id) {
-            name = Some(id.clone());
-            self.advance();
-        }
+        let name = self.expect_name()?.clone();
+        self.advance();
 
-        self.expect(TokenKind::LParen)?;
+        self.expect(TokenKind::LCurly)?;
         self.advance();
 
-        let mut params: Vec<TypeMapping> = Vec::new();
+        let fields = self.parse_comma_list(TokenKind::RCurly, Self::parse_type_mapping)?;
 
-        while let Err(_) = self.expect(TokenKind::RParen) {
-            let param_name = self.curr_expect(TokenKind::Id)?.clone();
-            self.advance();
-            self.expect(TokenKind::Colon)?;
-            self.advance();
-            let param_type = self.parse_type()?;
-            params.push(TypeMapping::new(
-                Expression::Id {
-                    name: param_name.clone(),
-                },
-                param_type,
+        Ok(Statement::StructDecl {
+            name,
+            fields,
+            span: self.span_from(&start),
+        })
+    }
+
+    fn parse_import(&mut self) -> Result<Statement, CompileError> {
+        let start = self.curr_expect(TokenKind::Import)?.location.clone();
+
+        // imports are resolved before anything runs, so they cannot depend
+        // on control flow
+        if self.block_depth > 0 {
+            return Err(CompileError::syntax(
+                "'import' is only allowed at the top level".into(),
+                start,
             ));
-            if let Ok(_) = self.expect(TokenKind::Comma) {
-                self.advance();
-            }
         }
         self.advance();
 
-        let mut return_type: Option<Type> = None;
-        if let Ok(_) = self.expect(TokenKind::Colon) {
-            self.advance();
-            self.expect(TokenKind::Colon)?;
-            self.advance();
+        let path = self.curr_expect(TokenKind::String)?.clone();
+        self.advance();
+        self.expect(TokenKind::Semicolon)?;
+        self.advance();
 
-            return_type = Some(self.parse_type()?);
-        }
+        Ok(Statement::Import {
+            path,
+            span: self.span_from(&start),
+        })
+    }
 
-        self.expect(TokenKind::LCurly)?;
+    fn parse_block(&mut self) -> Result<Vec<Statement>, CompileError> {
+        let l_curly = self.curr_expect(TokenKind::LCurly)?.clone();
         self.advance();
 
         let mut body: Vec<Statement> = Vec::new();
 
-        while let Err(_) = self.expect(TokenKind::RCurly) {
-            let stmt = self.parse_stmt()?;
+        while !self.curr_is(TokenKind::RCurly) {
+            // blocks count toward the nesting limit, since function literals
+            // put them inside expressions
+            self.block_depth += 1;
+            let stmt = self.nested(Self::parse_stmt);
+            self.block_depth -= 1;
+            let stmt = stmt?;
             if let Statement::Halt = stmt {
-                return Err(format!(
-                    "unexpected end of input in function body at line: {}, col: {}",
-                    fn_keyword.location.line, fn_keyword.location.col
+                return Err(CompileError::syntax(
+                    "unterminated block opened".into(),
+                    l_curly.location.clone(),
                 ));
             }
 
@@ -267,138 +502,781 @@ impl Parser {
         }
 
         self.advance(); // skip }
-        if let Ok(_) = self.expect(TokenKind::LParen) {
-            self.advance();
-            let mut args: Vec<Expression> = Vec::new();
-
-            while let Some(curr) = self.curr() {
-                match curr.kind {
-                    TokenKind::RParen => {
-                        self.advance();
-                        break;
-                    }
-                    _ => {
-                        args.push(self.parse_expr()?);
-                        if let Ok(_) = self.expect(TokenKind::Comma) {
-                            self.advance();
-                        }
-                    }
-                }
-            }
+        Ok(body)
+    }
 
-            return Ok(Expression::FunctionCall {
-                callee: Expression::FunctionLiteral {
-                    name,
-                    params,
-                    return_type,
-                    body,
-                }
-                .into(),
-                args: args,
-            });
-        }
+    fn parse_expr(&mut self) -> Result<Expression, CompileError> {
+        self.nested(Self::parse_assignment)
+    }
 
-        Ok(Expression::FunctionLiteral {
-            name,
-            params,
-            return_type,
-            body,
-        })
+    /// Runs `parse` one nesting level deeper, failing cleanly once the
+    /// parser is more than `max_depth` levels in. Levels `parse` takes with
+    /// `descend` are given back when it returns.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, CompileError>,
+    ) -> Result<T, CompileError> {
+        let depth = self.depth;
+        let result = self.descend().and_then(|_| parse(self));
+        self.depth = depth;
+
+        result
     }
 
-    fn parse_type(&mut self) -> Result<Type, String> {
-        if let Some(curr) = self.curr() {
-            match curr.kind {
-                TokenKind::Int => {
-                    self.advance();
-                    return Ok(Type::Int);
-                }
-                _ => {
-                    return Err(format!(
-                        "Expected type at line: {}, col: {}, but got: {}",
-                        curr.location.line, curr.location.col, curr.kind
-                    ));
-                }
-            }
+    /// Takes one more nesting level for a node built in a loop rather than
+    /// by a recursive call, like each `+` of `1 + 1 + 1` or each call of
+    /// `f()()()`, which nest just as deeply in the tree.
+    fn descend(&mut self) -> Result<(), CompileError> {
+        if self.depth >= self.max_depth {
+            let location = match self.curr() {
+                Some(curr) => curr.location.clone(),
+                None => self.last_location(),
+            };
+            return Err(CompileError::syntax(
+                "expression nesting too deep".into(),
+                location,
+            ));
         }
 
-        return Err("Expected type at the end of stream".into());
+        self.depth += 1;
+        Ok(())
     }
 
-    fn curr(&self) -> Option<&Token> {
-        self.peek_off(0)
-    }
+    fn parse_assignment(&mut self) -> Result<Expression, CompileError> {
+        let target = self.parse_ternary()?;
+
+        if let Some(eq) = self.curr()
+            && eq.kind.is_one_of(&[
+                TokenKind::Eq,
+                TokenKind::PlusEq,
+                TokenKind::MinusEq,
+                TokenKind::StarEq,
+                TokenKind::SlashEq,
+            ])
+        {
+            let eq = eq.clone();
+            self.advance();
 
-    fn curr_expect(&self, kind: TokenKind) -> Result<&Token, String> {
-        if let Some(curr) = self.curr() {
-            if curr.kind != kind {
-                return Err(format!(
-                    "expected {:?} at line {} col {}",
-                    kind, curr.location.line, curr.location.col
+            let value = self.nested(Self::parse_assignment)?;
+
+            // `(x) = v` assigns to `x`, the parentheses carry no meaning here
+            let target = target.ungrouped().clone();
+            if !matches!(target, Expression::Id { .. } | Expression::Index { .. }) {
+                let location = target.location().clone();
+                return Err(CompileError::syntax(
+                    format!(
+                        "cannot assign to '{}', expected a variable or an index",
+                        target
+                    ),
+                    location,
                 ));
             }
 
-            return Ok(curr);
-        }
+            let span = target.span().to(value.span());
 
-        if let Some(last) = self.tokens.last() {
-            return Err(format!(
-                "input expected {} after token at line {} col {} ",
-                kind, last.location.line, last.location.col
-            ));
+            return Ok(Expression::Assign {
+                target: target.into(),
+                op: Self::compound_operator(&eq),
+                value: value.into(),
+                span,
+            });
         }
 
-        Err(format!("input expected {}", kind))
+        Ok(target)
     }
 
-    fn expect(&self, kind: TokenKind) -> Result<(), String> {
-        if let Some(curr) = self.curr() {
-            if curr.kind != kind {
-                return Err(format!(
-                    "expected {:?} at line {} col {}",
-                    kind, curr.location.line, curr.location.col
-                ));
-            }
+    /// `cond ? a : b` binds looser than every binary operator and nests to
+    /// the right, so `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn parse_ternary(&mut self) -> Result<Expression, CompileError> {
+        let condition = self.parse_binary(0)?;
 
-            return Ok(());
+        if !self.curr_is(TokenKind::Question) {
+            return Ok(condition);
         }
+        self.advance();
 
-        if let Some(last) = self.tokens.last() {
-            return Err(format!(
-                "input expected {} after token at line {} col {} ",
-                kind, last.location.line, last.location.col
-            ));
-        }
+        let then_expr = self.parse_expr()?;
+        self.expect(TokenKind::Colon)?;
+        self.advance();
+        let else_expr = self.nested(Self::parse_ternary)?;
 
-        Err(format!("input expected {}", kind))
+        Ok(Expression::Ternary {
+            span: condition.span().to(else_expr.span()),
+            condition: condition.into(),
+            then_expr: then_expr.into(),
+            else_expr: else_expr.into(),
+        })
     }
 
-    fn expect_off(&self, kind: TokenKind, offset: usize) -> Result<(), String> {
-        if let Some(token) = self.peek_off(offset) {
-            if token.kind != kind {
-                return Err(format!(
-                    "expected {:?} at line {} col {}",
-                    kind, token.location.line, token.location.col
-                ));
-            }
+    fn parse_binary(&mut self, min_bp: usize) -> Result<Expression, CompileError> {
+        let mut lhs = self.parse_prefix()?;
 
-            return Ok(());
-        }
+        while let Some(op) = self.curr() {
+            if Self::is_binary_operator(op.kind) {
+                let op = op.clone();
+                let (l_bp, r_bp) = Self::get_binding_power(op.kind);
 
-        if let Some(last) = self.tokens.last() {
-            return Err(format!(
-                "input expected {} after token at line {} col {} ",
-                kind, last.location.line, last.location.col
-            ));
+                if l_bp < min_bp {
+                    break;
+                }
+
+                self.descend()?;
+                self.advance();
+
+                let rhs = self.nested(|parser| parser.parse_binary(r_bp))?;
+
+                lhs = Expression::Binary {
+                    span: lhs.span().to(rhs.span()),
+                    lhs: lhs.into(),
+                    op,
+                    rhs: rhs.into(),
+                }
+            } else {
+                break;
+            }
         }
 
-        Err(format!("input expected {}", kind))
+        Ok(lhs)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expression, CompileError> {
+        if let Some(curr) = self.curr() {
+            let curr = curr.clone();
+            match curr.kind {
+                TokenKind::Plus | TokenKind::Minus | TokenKind::Bang => {
+                    self.advance();
+                    let expr = self.nested(Self::parse_prefix)?;
+                    return Ok(Expression::Unary {
+                        span: curr.span().to(expr.span()),
+                        op: curr,
+                        expr: expr.into(),
+                    });
+                }
+                // there are no increment/decrement operators, so in prefix
+                // position `--2` is two negations and `++2` two unary pluses
+                TokenKind::Decr | TokenKind::Inc => {
+                    self.advance();
+                    let kind = if curr.kind.is(TokenKind::Decr) {
+                        TokenKind::Minus
+                    } else {
+                        TokenKind::Plus
+                    };
+                    let op = Token {
+                        kind,
+                        literal: curr.literal[..1].into(),
+                        ..curr.clone()
+                    };
+                    let expr = self.nested(Self::parse_prefix)?;
+                    let span = curr.span().to(expr.span());
+                    let inner = Expression::Unary {
+                        op: op.clone(),
+                        expr: expr.into(),
+                        span: span.clone(),
+                    };
+                    return Ok(Expression::Unary {
+                        op,
+                        expr: inner.into(),
+                        span,
+                    });
+                }
+                TokenKind::EOF => return Err(self.unexpected_end()),
+                _ => {
+                    return self.parse_postfix();
+                }
+            }
+        }
+
+        Err(self.unexpected_end())
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expression, CompileError> {
+        let mut expr = self.parse_atom()?;
+
+        loop {
+            if self.curr_is(TokenKind::LBracket) {
+                self.descend()?;
+                self.advance();
+                let index = self.parse_expr()?;
+                self.expect(TokenKind::RBracket)?;
+                self.advance();
+
+                expr = Expression::Index {
+                    span: self.span_from(&expr.span().start),
+                    target: expr.into(),
+                    index: index.into(),
+                };
+            } else if self.curr_is(TokenKind::LParen) {
+                // any expression can be called, so `f()()` calls what `f()` returns
+                self.descend()?;
+                self.advance();
+                let (args, named_args) = self.parse_call_args()?;
+
+                expr = Expression::FunctionCall {
+                    span: self.span_from(&expr.span().start),
+                    callee: expr.into(),
+                    args,
+                    named_args,
+                };
+            } else if self.curr_is(TokenKind::Dot) {
+                self.descend()?;
+                self.advance();
+                let field = self.curr_expect(TokenKind::Id)?.clone();
+                self.advance();
+
+                expr = Expression::Member {
+                    span: expr.span().to(&field.span()),
+                    target: expr.into(),
+                    field,
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_atom(&mut self) -> Result<Expression, CompileError> {
+        if let Some(curr) = self.curr() {
+            let curr = curr.clone();
+            match curr.kind {
+                TokenKind::Fn => {
+                    return self.parse_function_literal();
+                }
+                TokenKind::Id => {
+                    return self.parse_id();
+                }
+                TokenKind::Int => {
+                    self.advance();
+                    return Ok(Expression::Int {
+                        span: curr.span(),
+                        value: curr,
+                    });
+                }
+                TokenKind::Float => {
+                    self.advance();
+                    return Ok(Expression::Float {
+                        span: curr.span(),
+                        value: curr,
+                    });
+                }
+                TokenKind::String => {
+                    self.advance();
+                    return Ok(Expression::Str {
+                        span: curr.span(),
+                        value: curr,
+                    });
+                }
+                TokenKind::Char => {
+                    self.advance();
+                    return Ok(Expression::Char {
+                        span: curr.span(),
+                        value: curr,
+                    });
+                }
+                TokenKind::True | TokenKind::False => {
+                    self.advance();
+                    return Ok(Expression::Bool {
+                        span: curr.span(),
+                        value: curr,
+                    });
+                }
+                TokenKind::LBracket => {
+                    return self.parse_array();
+                }
+                TokenKind::LParen => {
+                    return self.parse_group_or_tuple();
+                }
+                TokenKind::EOF => return Err(self.unexpected_end()),
+                _ => {
+                    return Err(CompileError::syntax(
+                        format!("unexpected token '{}' ({:?})", curr.literal, curr.kind),
+                        curr.location.clone(),
+                    ));
+                }
+            }
+        }
+
+        Err(self.unexpected_end())
+    }
+
+    fn parse_array(&mut self) -> Result<Expression, CompileError> {
+        let start = self.curr_expect(TokenKind::LBracket)?.location.clone();
+        self.advance();
+
+        let elements = self.parse_comma_list(TokenKind::RBracket, Self::parse_expr)?;
+
+        Ok(Expression::Array {
+            elements,
+            span: self.span_from(&start),
+        })
+    }
+
+    /// `(a)` is a grouping; a comma makes it a tuple, as in `(a,)` or `(a, b)`.
+    fn parse_group_or_tuple(&mut self) -> Result<Expression, CompileError> {
+        let start = self.curr_expect(TokenKind::LParen)?.location.clone();
+        self.advance();
+
+        if self.curr_is(TokenKind::RParen) {
+            self.advance();
+            return Ok(Expression::Tuple {
+                elements: Vec::new(),
+                span: self.span_from(&start),
+            });
+        }
+
+        let first = self.parse_expr()?;
+        if !self.curr_is(TokenKind::Comma) {
+            self.expect(TokenKind::RParen)?;
+            self.advance();
+            return Ok(Expression::Grouping {
+                inner: first.into(),
+                span: self.span_from(&start),
+            });
+        }
+        self.advance();
+
+        let mut elements = vec![first];
+        elements.extend(self.parse_comma_list(TokenKind::RParen, Self::parse_expr)?);
+
+        Ok(Expression::Tuple {
+            elements,
+            span: self.span_from(&start),
+        })
+    }
+
+    fn parse_id(&mut self) -> Result<Expression, CompileError> {
+        self.expect(TokenKind::Id)?;
+
+        if self.peek_is(TokenKind::LCurly) {
+            return self.parse_struct_literal();
+        }
+
+        if let Some(curr) = self.curr().cloned() {
+            self.advance();
+            return Ok(Expression::Id {
+                span: curr.span(),
+                name: curr,
+            });
+        }
+
+        Err(self.unexpected_end())
+    }
+
+    fn parse_struct_literal(&mut self) -> Result<Expression, CompileError> {
+        let name = self.curr_expect(TokenKind::Id)?.clone();
+        self.advance();
+
+        self.expect(TokenKind::LCurly)?;
+        self.advance();
+
+        let fields = self.parse_comma_list(TokenKind::RCurly, |parser| {
+            let field = parser.curr_expect(TokenKind::Id)?.clone();
+            parser.advance();
+            parser.expect(TokenKind::Colon)?;
+            parser.advance();
+            Ok((field, parser.parse_expr()?))
+        })?;
+
+        Ok(Expression::StructLiteral {
+            span: self.span_from(&name.location),
+            name,
+            fields,
+        })
+    }
+
+    /// Parses the arguments after a call's `(`. Named arguments are written
+    /// `name: value` and must come after every positional one.
+    fn parse_call_args(&mut self) -> Result<CallArgs, CompileError> {
+        let mut args: Vec<Expression> = Vec::new();
+        let mut named_args: Vec<(Token, Expression)> = Vec::new();
+
+        for arg in self.parse_comma_list(TokenKind::RParen, Self::parse_argument)? {
+            match arg {
+                Argument::Positional(value) if !named_args.is_empty() => {
+                    let location = value.span().start.clone();
+                    return Err(CompileError::syntax(
+                        "positional argument after named argument".into(),
+                        location,
+                    ));
+                }
+                Argument::Positional(value) => args.push(value),
+                Argument::Named(name, value) => named_args.push((name, value)),
+            }
+        }
+
+        Ok((args, named_args))
+    }
+
+    fn parse_argument(&mut self) -> Result<Argument, CompileError> {
+        if self.curr_is(TokenKind::Id) && self.peek_is(TokenKind::Colon) {
+            let name = self.curr_expect(TokenKind::Id)?.clone();
+            self.advance();
+            self.advance();
+
+            return Ok(Argument::Named(name, self.parse_expr()?));
+        }
+
+        Ok(Argument::Positional(self.parse_expr()?))
+    }
+
+    fn parse_function_literal(&mut self) -> Result<Expression, CompileError> {
+        let start = self.curr_expect(TokenKind::Fn)?.location.clone();
+        self.advance();
+
+        let mut name: Option<Token> = None;
+
+        if let Ok(id) = self.curr_expect(TokenKind::Id) {
+            name = Some(id.clone());
+            self.advance();
+        } else if let Some(curr) = self.curr()
+            && !curr.kind.is(TokenKind::LParen)
+            && TokenKind::is_keyword(&curr.literal)
+        {
+            return Err(Self::keyword_as_name(curr));
+        }
+
+        self.expect(TokenKind::LParen)?;
+        self.advance();
+
+        let params = self.parse_comma_list(TokenKind::RParen, Self::parse_type_mapping)?;
+        Self::check_unique_params(&params)?;
+
+        let mut return_type: Option<Type> = None;
+        if self.curr_is(TokenKind::Arrow) {
+            self.advance();
+
+            return_type = Some(self.parse_type()?);
+        } else if self.curr_is(TokenKind::Colon) {
+            // `::` is the old return type syntax, kept until callers move to `->`.
+            self.advance();
+            self.expect(TokenKind::Colon)?;
+            self.advance();
+
+            return_type = Some(self.parse_type()?);
+        }
+
+        self.function_depth += 1;
+        let body = self.parse_block();
+        self.function_depth -= 1;
+        let body = body?;
+
+        Ok(Expression::FunctionLiteral {
+            name,
+            params,
+            return_type,
+            body,
+            span: self.span_from(&start),
+        })
+    }
+
+    /// A type followed by any number of `?`, so `int??` is an optional
+    /// optional int.
+    fn parse_type(&mut self) -> Result<Type, CompileError> {
+        let mut t = self.parse_type_atom()?;
+
+        while self.curr_is(TokenKind::Question) {
+            self.advance();
+            t = Type::Optional { inner: t.into() };
+        }
+
+        Ok(t)
+    }
+
+    fn parse_type_atom(&mut self) -> Result<Type, CompileError> {
+        if let Some(curr) = self.curr() {
+            match curr.kind {
+                TokenKind::Int => {
+                    self.advance();
+                    return Ok(Type::Int);
+                }
+                TokenKind::Bool => {
+                    self.advance();
+                    return Ok(Type::Bool);
+                }
+                TokenKind::LBracket => {
+                    self.advance();
+                    let element = self.parse_type()?;
+                    self.expect(TokenKind::RBracket)?;
+                    self.advance();
+                    return Ok(Type::Array {
+                        element: element.into(),
+                    });
+                }
+                TokenKind::Id if &*curr.literal == "string" => {
+                    self.advance();
+                    return Ok(Type::String);
+                }
+                TokenKind::Id if &*curr.literal == "void" => {
+                    self.advance();
+                    return Ok(Type::Unit);
+                }
+                TokenKind::Id => {
+                    let name = curr.literal.to_string();
+                    self.advance();
+                    return Ok(Type::Named { name });
+                }
+                TokenKind::Fn => {
+                    return self.parse_function_type();
+                }
+                TokenKind::LParen => {
+                    return self.parse_tuple_type();
+                }
+                _ => {
+                    return Err(CompileError::syntax(
+                        format!("Expected type, but got: {}", curr.kind),
+                        curr.location.clone(),
+                    ));
+                }
+            }
+        }
+
+        Err(CompileError::syntax(
+            "Expected type at the end of stream".into(),
+            self.last_location(),
+        ))
+    }
+
+    /// Mirrors `parse_group_or_tuple`: `(int)` is `int`, `(int,)` and
+    /// `(int, bool)` are tuples.
+    fn parse_tuple_type(&mut self) -> Result<Type, CompileError> {
+        self.expect(TokenKind::LParen)?;
+        self.advance();
+
+        if self.curr_is(TokenKind::RParen) {
+            self.advance();
+            return Ok(Type::Tuple {
+                elements: Vec::new(),
+            });
+        }
+
+        let first = self.parse_type()?;
+        if !self.curr_is(TokenKind::Comma) {
+            self.expect(TokenKind::RParen)?;
+            self.advance();
+            return Ok(first);
+        }
+        self.advance();
+
+        let mut elements = vec![first];
+        elements.extend(self.parse_comma_list(TokenKind::RParen, Self::parse_type)?);
+
+        Ok(Type::Tuple { elements })
+    }
+
+    fn parse_function_type(&mut self) -> Result<Type, CompileError> {
+        self.expect(TokenKind::Fn)?;
+        self.advance();
+        self.expect(TokenKind::LParen)?;
+        self.advance();
+
+        let param_types = self.parse_comma_list(TokenKind::RParen, Self::parse_type)?;
+
+        self.expect(TokenKind::Arrow)?;
+        self.advance();
+
+        let return_type = self.parse_type()?;
+
+        Ok(Type::Function {
+            return_type: return_type.into(),
+            param_types,
+        })
+    }
+
+    fn parse_type_mapping(&mut self) -> Result<TypeMapping, CompileError> {
+        let name = self.expect_name()?.clone();
+        self.advance();
+        self.expect(TokenKind::Colon)?;
+        self.advance();
+        let t = self.parse_type()?;
+
+        Ok(TypeMapping::new(
+            Expression::Id {
+                span: name.span(),
+                name,
+            },
+            t,
+        ))
+    }
+
+    /// Parses `item, item, ...` up to and including `close`. The opening
+    /// delimiter must already be consumed. A trailing comma is allowed, a
+    /// leading or doubled one is not.
+    fn parse_comma_list<T>(
+        &mut self,
+        close: TokenKind,
+        mut parse_item: impl FnMut(&mut Self) -> Result<T, CompileError>,
+    ) -> Result<Vec<T>, CompileError> {
+        let mut items: Vec<T> = Vec::new();
+
+        while !self.curr_is(close) {
+            if let Some(comma) = self.curr()
+                && comma.kind.is(TokenKind::Comma)
+            {
+                return Err(CompileError::syntax(
+                    format!("unexpected ',', expected an item or '{}'", close),
+                    comma.location.clone(),
+                ));
+            }
+
+            items.push(parse_item(self)?);
+
+            if !self.curr_is(TokenKind::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        self.expect(close)?;
+        self.advance();
+
+        Ok(items)
+    }
+
+    /// Span from `start` to the end of the most recently consumed token.
+    fn span_from(&self, start: &Location) -> Span {
+        let end = match self
+            .pos
+            .checked_sub(1)
+            .and_then(|prev| self.tokens.get(prev))
+        {
+            Some(prev) => prev.end.clone(),
+            None => start.clone(),
+        };
+
+        Span::new(start.clone(), end)
+    }
+
+    /// The token stream ran out in the middle of an expression. Points at
+    /// the last token consumed, since there is nothing after it to blame.
+    fn unexpected_end(&self) -> CompileError {
+        let Some(last) = self.pos.checked_sub(1).and_then(|i| self.tokens.get(i)) else {
+            return CompileError::syntax("unexpected end of input".into(), self.last_location());
+        };
+
+        CompileError::syntax(
+            format!("unexpected end of input after '{}'", last.literal),
+            last.location.clone(),
+        )
+    }
+
+    fn last_location(&self) -> Location {
+        self.tokens
+            .last()
+            .map(|last| last.location.clone())
+            .unwrap_or(Location::new(1, 1))
+    }
+
+    fn curr(&self) -> Option<&Token> {
+        self.peek_off(0)
+    }
+
+    fn curr_expect(&self, kind: TokenKind) -> Result<&Token, CompileError> {
+        if let Some(curr) = self.curr() {
+            if curr.kind != kind {
+                return Err(CompileError::syntax(
+                    format!("expected {:?}", kind),
+                    curr.location.clone(),
+                ));
+            }
+
+            return Ok(curr);
+        }
+
+        if let Some(last) = self.tokens.last() {
+            return Err(CompileError::syntax(
+                format!("input expected {} after token", kind),
+                last.location.clone(),
+            ));
+        }
+
+        Err(CompileError::syntax(
+            format!("input expected {}", kind),
+            self.last_location(),
+        ))
+    }
+
+    /// Like `curr_expect(TokenKind::Id)`, but calls out a reserved keyword
+    /// sitting where a name is being declared.
+    fn expect_name(&self) -> Result<&Token, CompileError> {
+        match self.curr() {
+            Some(curr) if !curr.kind.is(TokenKind::Id) && TokenKind::is_keyword(&curr.literal) => {
+                Err(Self::keyword_as_name(curr))
+            }
+            _ => self.curr_expect(TokenKind::Id),
+        }
+    }
+
+    fn keyword_as_name(token: &Token) -> CompileError {
+        CompileError::syntax(
+            format!(
+                "'{}' is a reserved keyword and cannot be used as a name",
+                token.literal
+            ),
+            token.location.clone(),
+        )
+    }
+
+    /// Rejects a parameter list that declares the same name twice, pointing at
+    /// the second declaration.
+    fn check_unique_params(params: &[TypeMapping]) -> Result<(), CompileError> {
+        let mut seen: HashSet<&str> = HashSet::new();
+
+        for param in params {
+            let Expression::Id { name, .. } = &param.expr else {
+                continue;
+            };
+
+            if !seen.insert(&name.literal) {
+                return Err(CompileError::syntax(
+                    format!("duplicate parameter '{}'", name.literal),
+                    name.location.clone(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expect(&self, kind: TokenKind) -> Result<(), CompileError> {
+        if let Some(curr) = self.curr() {
+            if curr.kind != kind {
+                return Err(CompileError::syntax(
+                    format!("expected {:?}", kind),
+                    curr.location.clone(),
+                ));
+            }
+
+            return Ok(());
+        }
+
+        if let Some(last) = self.tokens.last() {
+            return Err(CompileError::syntax(
+                format!("input expected {} after token", kind),
+                last.location.clone(),
+            ));
+        }
+
+        Err(CompileError::syntax(
+            format!("input expected {}", kind),
+            self.last_location(),
+        ))
     }
 
     fn peek(&self) -> Option<&Token> {
         self.peek_off(1)
     }
 
+    /// Whether the current token is of `kind`; false past the end.
+    fn curr_is(&self, kind: TokenKind) -> bool {
+        self.curr().is_some_and(|curr| curr.kind.is(kind))
+    }
+
+    /// Whether the token after the current one is of `kind`; false past the end.
+    fn peek_is(&self, kind: TokenKind) -> bool {
+        self.peek().is_some_and(|peek| peek.kind.is(kind))
+    }
+
     fn peek_off(&self, offset: usize) -> Option<&Token> {
         if self.pos + offset >= self.tokens.len() {
             return None;
@@ -409,22 +1287,730 @@ impl Parser {
 
     fn advance(&mut self) {
         self.pos += 1;
+        self.fill();
     }
 
-    fn get_binding_power(op: TokenKind) -> (usize, usize) {
-        match op {
-            TokenKind::Plus | TokenKind::Minus => (1, 2),
-            TokenKind::Star | TokenKind::Slash => (3, 4),
-            _ => (0, 0),
+    /// Buffers enough tokens from `source` for `curr` and `peek_off`.
+    fn fill(&mut self) {
+        while self.tokens.len() <= self.pos + LOOKAHEAD {
+            match self.source.next() {
+                Some(Ok(token)) => self.tokens.push(token),
+                Some(Err(err)) => self.lex_errors.push(err),
+                None => break,
+            }
         }
     }
 
+    fn compound_operator(eq: &Token) -> Option<Token> {
+        let (kind, literal) = match eq.kind {
+            TokenKind::PlusEq => (TokenKind::Plus, "+"),
+            TokenKind::MinusEq => (TokenKind::Minus, "-"),
+            TokenKind::StarEq => (TokenKind::Star, "*"),
+            TokenKind::SlashEq => (TokenKind::Slash, "/"),
+            _ => return None,
+        };
+
+        Some(Token::new(
+            kind,
+            literal.into(),
+            eq.location.clone(),
+            eq.location.clone(),
+        ))
+    }
+
+    pub(crate) fn get_binding_power(op: TokenKind) -> (usize, usize) {
+        Self::binary_operator(op).unwrap_or((0, 0))
+    }
+
     fn is_binary_operator(kind: TokenKind) -> bool {
-        kind.is_one_of(&[
-            TokenKind::Plus,
-            TokenKind::Minus,
-            TokenKind::Star,
-            TokenKind::Slash,
-        ])
+        Self::binary_operator(kind).is_some()
+    }
+
+    fn binary_operator(kind: TokenKind) -> Option<(usize, usize)> {
+        BINARY_OPERATORS
+            .iter()
+            .find(|(op, _)| *op == kind)
+            .map(|(_, bp)| *bp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::lexer::Lexer;
+
+    fn parse(src: &str) -> Result<Vec<Statement>, Vec<CompileError>> {
+        let tokens = Lexer::from_str(src).lex().expect("source should lex");
+        Parser::new(tokens).parse()
+    }
+
+    fn dump(src: &str) -> Vec<String> {
+        let stmts = parse(src).unwrap_or_else(|errs| panic!("{:?}", errs));
+        stmts.iter().map(|stmt| stmt.to_string()).collect()
+    }
+
+    fn errors(src: &str) -> Vec<String> {
+        match parse(src) {
+            Ok(stmts) => panic!("expected a syntax error, got {:?}", stmts),
+            Err(errs) => errs.iter().map(|err| err.to_string()).collect(),
+        }
+    }
+
+    /// The single expression statement of `src` with every operator wrapped
+    /// in parentheses, to show how the parser grouped it.
+    fn shape(src: &str) -> String {
+        fn render(expr: &Expression) -> String {
+            match expr {
+                Expression::Binary { lhs, op, rhs, .. } => {
+                    format!("({} {} {})", render(lhs), op.kind, render(rhs))
+                }
+                Expression::Unary { op, expr, .. } => format!("({}{})", op.kind, render(expr)),
+                Expression::Ternary {
+                    condition,
+                    then_expr,
+                    else_expr,
+                    ..
+                } => format!(
+                    "({} ? {} : {})",
+                    render(condition),
+                    render(then_expr),
+                    render(else_expr)
+                ),
+                Expression::Assign {
+                    target, op, value, ..
+                } => {
+                    let op = op.as_ref().map_or(String::new(), |op| op.kind.to_string());
+                    format!("({} {}= {})", render(target), op, render(value))
+                }
+                Expression::Grouping { inner, .. } => render(inner),
+                other => other.to_string(),
+            }
+        }
+
+        match parse(src)
+            .unwrap_or_else(|errs| panic!("{:?}", errs))
+            .as_slice()
+        {
+            [Statement::ExpressionStatement { expression, .. }] => render(expression),
+            stmts => panic!("expected a single expression statement, got {:?}", stmts),
+        }
+    }
+
+    #[test]
+    fn grouping_overrides_precedence() {
+        assert_eq!(shape("(1 + 2) * 3;"), "((1 + 2) * 3)");
+        assert_eq!(shape("1 + 2 * 3;"), "(1 + (2 * 3))");
+    }
+
+    #[test]
+    fn parses_float_literals() {
+        assert_eq!(
+            dump("fn main() { return 2.5 + 1.0; }"),
+            ["fn main() -> void { return 2.5 + 1.0 }"]
+        );
+    }
+
+    #[test]
+    fn parses_string_literals() {
+        assert_eq!(
+            dump(r#"fn greet() { return "hello"; }"#),
+            [r#"fn greet() -> void { return "hello" }"#]
+        );
+    }
+
+    #[test]
+    fn comparisons_bind_looser_than_arithmetic() {
+        assert_eq!(shape("1 < 2 + 3;"), "(1 < (2 + 3))");
+        assert_eq!(shape("1 + 2 > 3 - 4;"), "((1 + 2) > (3 - 4))");
+    }
+
+    #[test]
+    fn parses_boolean_literals_and_type() {
+        assert_eq!(
+            dump("fn f() :: bool { return true; }"),
+            ["fn f() -> bool { return true }"]
+        );
+        assert_eq!(dump("false;"), ["false;"]);
+    }
+
+    #[test]
+    fn parses_let_statements() {
+        assert_eq!(dump("let x = 3;"), ["let x = 3"]);
+        assert_eq!(dump("let y: int = 1 + 2;"), ["let y: int = 1 + 2"]);
+    }
+
+    #[test]
+    fn let_without_a_name_is_an_error() {
+        assert_eq!(
+            errors("let = 5;"),
+            ["expected variable name after 'let', but got = at line: 1, col: 5"]
+        );
+    }
+
+    #[test]
+    fn parses_if_statements() {
+        assert_eq!(dump("if (x) { 1; }"), ["if (x) { 1; }"]);
+        assert_eq!(
+            dump("if (x) { 1; } else { 2; }"),
+            ["if (x) { 1; } else { 2; }"]
+        );
+        assert_eq!(
+            dump("if (x) { 1; } else if (y) { 2; } else { 3; }"),
+            ["if (x) { 1; } else if (y) { 2; } else { 3; }"]
+        );
+    }
+
+    #[test]
+    fn parses_while_loops() {
+        assert_eq!(
+            dump("while (1 < 2) { x = x + 1; }"),
+            ["while (1 < 2) { x = x + 1; }"]
+        );
+    }
+
+    #[test]
+    fn unterminated_while_body_is_an_error() {
+        assert_eq!(
+            errors("while (1 < 2) { x = 1;"),
+            ["unterminated block opened at line: 1, col: 15"]
+        );
+    }
+
+    #[test]
+    fn parses_every_statement_of_a_block() {
+        let stmts = parse("fn f() { let a = 1; let b = 2; a + b; return b; }").unwrap();
+        let Statement::ExpressionStatement {
+            expression: Expression::FunctionLiteral { body, .. },
+            ..
+        } = &stmts[0]
+        else {
+            panic!("expected a function literal, got {:?}", stmts[0]);
+        };
+
+        let body: Vec<String> = body.iter().map(|stmt| stmt.to_string()).collect();
+        assert_eq!(body, ["let a = 1", "let b = 2", "a + b;", "return b"]);
+    }
+
+    #[test]
+    fn syntax_errors_carry_the_token_location() {
+        let errs = parse("let a = 1;\nlet = 2;").unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].kind, ErrorKind::Syntax);
+        assert_eq!((errs[0].location.line, errs[0].location.col), (2, 5));
+    }
+
+    #[test]
+    fn modulo_binds_like_multiplication() {
+        assert_eq!(shape("7 % 3 + 1;"), "((7 % 3) + 1)");
+        assert_eq!(shape("2 * 3 % 4;"), "((2 * 3) % 4)");
+    }
+
+    #[test]
+    fn parses_logical_operators() {
+        assert_eq!(shape("1 < 2 && 3 > 1;"), "((1 < 2) && (3 > 1))");
+        assert_eq!(shape("a || b && !c;"), "(a || (b && (!c)))");
+        assert_eq!(dump("!(0);"), ["!(0);"]);
+    }
+
+    #[test]
+    fn assignment_chains_right_to_left() {
+        assert_eq!(shape("x = y = 3;"), "(x = (y = 3))");
+    }
+
+    #[test]
+    fn assignment_to_a_non_lvalue_is_an_error() {
+        assert_eq!(
+            errors("1 = 2;"),
+            ["cannot assign to '1', expected a variable or an index at line: 1, col: 1"]
+        );
+    }
+
+    #[test]
+    fn parses_arrays_and_indexing() {
+        assert_eq!(dump("[[1], [2]];"), ["[[1], [2]];"]);
+        assert_eq!(dump("a[0][1];"), ["a[0][1];"]);
+        assert_eq!(dump("let a: [[int]] = [];"), ["let a: [[int]] = []"]);
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_eq!(shape("2 ^ 3 ^ 2;"), "(2 ^ (3 ^ 2))");
+        assert_eq!(shape("2 * 3 ^ 2;"), "(2 * (3 ^ 2))");
+    }
+
+    #[test]
+    fn stops_at_a_token_stream_without_eof() {
+        let mut tokens = Lexer::from_str("let x = 1 +").lex().unwrap();
+        tokens.pop();
+
+        let errs = Parser::new(tokens).parse().unwrap_err();
+        assert_eq!(errs.len(), 1);
+    }
+
+    #[test]
+    fn parses_function_types() {
+        assert_eq!(
+            dump("fn apply(f: fn(int) -> int, s: string) :: fn(int, int) -> int { return g; }"),
+            ["fn apply(f: fn(int) -> int, s: string) -> fn(int, int) -> int { return g }"]
+        );
+    }
+
+    #[test]
+    fn arrow_and_double_colon_return_types_agree() {
+        assert_eq!(
+            dump("fn f() -> int { return 1; }"),
+            dump("fn f() :: int { return 1; }")
+        );
+        assert_eq!(
+            Lexer::from_str("->").lex().unwrap()[0].kind,
+            TokenKind::Arrow
+        );
+    }
+
+    #[test]
+    fn unclosed_function_body_points_at_its_brace() {
+        assert_eq!(
+            errors("fn f() {\n  return 1;"),
+            ["unterminated block opened at line: 1, col: 8"]
+        );
+    }
+
+    #[test]
+    fn parses_compound_assignment() {
+        assert_eq!(dump("x += 2 * 3;"), ["x += 2 * 3;"]);
+        assert_eq!(dump("x /= y -= 1;"), ["x /= y -= 1;"]);
+    }
+
+    #[test]
+    fn compound_assignment_to_a_non_lvalue_is_an_error() {
+        assert_eq!(
+            errors("1 += 2;"),
+            ["cannot assign to '1', expected a variable or an index at line: 1, col: 1"]
+        );
+    }
+
+    #[test]
+    fn parses_chained_member_access() {
+        assert_eq!(dump("a.b.c;"), ["a.b.c;"]);
+        assert_eq!(dump("a.b[0].c;"), ["a.b[0].c;"]);
+    }
+
+    #[test]
+    fn parses_struct_declarations_and_literals() {
+        assert_eq!(
+            dump("struct Point { x: int, y: int } let p: Point = Point { x: 1, y: 2 };"),
+            [
+                "struct Point { x: int, y: int }",
+                "let p: Point = Point { x: 1, y: 2 }"
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_trailing_commas() {
+        assert_eq!(dump("f(1, 2,);"), ["f(1, 2);"]);
+        assert_eq!(
+            dump("fn g(a: int, b: int,) {}"),
+            ["fn g(a: int, b: int) -> void {  }"]
+        );
+    }
+
+    #[test]
+    fn rejects_leading_and_double_commas() {
+        assert_eq!(
+            errors("f(,1);"),
+            [
+                "unexpected ',', expected an item or ')' at line: 1, col: 3",
+                "expected Semicolon at line: 1, col: 5"
+            ]
+        );
+        assert_eq!(
+            errors("f(1,,2);"),
+            [
+                "unexpected ',', expected an item or ')' at line: 1, col: 5",
+                "expected Semicolon at line: 1, col: 7"
+            ]
+        );
+    }
+
+    #[test]
+    fn prefix_operators_nest() {
+        assert_eq!(shape("- - 2;"), "(-(-2))");
+        assert_eq!(shape("-+-3;"), "(-(+(-3)))");
+        assert_eq!(shape("-2 * 3;"), "((-2) * 3)");
+        // a `--` token in prefix position is two minuses, not a decrement
+        assert_eq!(shape("--2;"), "(-(-2))");
+        assert_eq!(shape("++x;"), "(+(+x))");
+    }
+
+    #[test]
+    fn parses_const_declarations() {
+        assert_eq!(dump("const PI: int = 3;"), ["const PI: int = 3"]);
+    }
+
+    #[test]
+    fn binary_span_covers_both_operands() {
+        let stmts = parse("let x =\n  ab + cd * ef;").unwrap();
+        let Statement::Let { value, .. } = &stmts[0] else {
+            panic!("expected a let, got {:?}", stmts[0]);
+        };
+
+        let span = value.span();
+        assert_eq!((span.start.line, span.start.col), (2, 3));
+        assert_eq!((span.end.line, span.end.col), (2, 14));
+        assert_eq!((value.location().line, value.location().col), (2, 3));
+    }
+
+    #[test]
+    fn parses_break_and_continue_in_loops() {
+        assert_eq!(
+            dump("while (x) { break; continue; }"),
+            ["while (x) { break; continue; }"]
+        );
+    }
+
+    #[test]
+    fn parses_for_loops() {
+        assert_eq!(
+            dump("for (let i = 0; i < 10; i = i + 1) { print(i); }"),
+            ["for (let i = 0; i < 10; i = i + 1) { print(i); }"]
+        );
+        assert_eq!(dump("for (;;) { break; }"), ["for (; ; ) { break; }"]);
+    }
+
+    #[test]
+    fn for_loop_missing_a_semicolon_is_an_error() {
+        assert_eq!(
+            errors("for (let i = 0 i < 10;) {}")[0],
+            "expected Semicolon at line: 1, col: 16"
+        );
+    }
+
+    #[test]
+    fn return_is_only_allowed_inside_functions() {
+        assert_eq!(
+            errors("return 5;"),
+            ["return outside of function at line: 1, col: 1"]
+        );
+        assert!(parse("fn f() -> int { let g = fn () -> int { return 1; }; return g(); }").is_ok());
+    }
+
+    #[test]
+    fn ternaries_nest_to_the_right() {
+        assert_eq!(shape("a ? b : c ? d : e;"), "(a ? b : (c ? d : e))");
+        assert_eq!(
+            shape("x = a < b ? a + 1 : b || c;"),
+            "(x = ((a < b) ? (a + 1) : (b || c)))"
+        );
+    }
+
+    #[test]
+    fn else_if_chains_nest() {
+        let stmts = parse("if (a) { 1; } else if (b) { 2; } else { 3; }").unwrap();
+
+        let mut depth = 0;
+        let mut stmt = &stmts[0];
+        while let Statement::If { else_branch, .. } = stmt {
+            depth += 1;
+            match else_branch.as_deref() {
+                Some([nested @ Statement::If { .. }]) => stmt = nested,
+                Some([Statement::ExpressionStatement { .. }]) => break,
+                other => panic!("unexpected else branch {:?}", other),
+            }
+        }
+        assert_eq!(depth, 2);
+    }
+
+    #[test]
+    fn parsing_from_an_iterator_matches_a_vec() {
+        let src = "fn f(a: int) -> int { return a[0] + g(1); } let y = f(2) ? 1 : 2;";
+        let tokens = Lexer::from_str(src).lex().unwrap();
+        let from_vec = Parser::new(tokens.clone()).parse().unwrap();
+        let from_iter = Parser::from_tokens(tokens).parse().unwrap();
+
+        let show = |stmts: &[Statement]| -> Vec<String> {
+            stmts.iter().map(|stmt| stmt.to_string()).collect()
+        };
+        assert_eq!(show(&from_iter), show(&from_vec));
+    }
+
+    #[test]
+    fn parsing_from_the_lexer_reports_every_error_in_order() {
+        let mut lexer = Lexer::from_str("let = 1;\nlet x = $;\n");
+        let errs = Parser::from_lexer(lexer.lex_all()).parse().unwrap_err();
+        let found: Vec<(ErrorKind, usize)> = errs
+            .iter()
+            .map(|err| (err.kind, err.location.line))
+            .collect();
+
+        assert_eq!(
+            found[..2],
+            [(ErrorKind::Syntax, 1), (ErrorKind::Lexical, 2)]
+        );
+    }
+
+    #[test]
+    fn keywords_cannot_be_names() {
+        assert_eq!(
+            errors("fn fn() {}")[0],
+            "'fn' is a reserved keyword and cannot be used as a name at line: 1, col: 4"
+        );
+        assert_eq!(
+            errors("fn f(int: int) {}")[0],
+            "'int' is a reserved keyword and cannot be used as a name at line: 1, col: 6"
+        );
+    }
+
+    #[test]
+    fn lone_semicolons_are_empty_statements() {
+        assert_eq!(dump(";;"), [";", ";"]);
+        assert_eq!(
+            dump("fn f() -> int { ; return 1;; }"),
+            ["fn f() -> int { ; return 1 ; }"]
+        );
+    }
+
+    /// Parses `src` on a thread with the stack size of a main thread, which
+    /// the default depth limit is sized for, and tells whether it hit it.
+    fn too_deep(src: &str) -> bool {
+        let src = src.to_string();
+        let parse = move || match parse(&src) {
+            Ok(_) => false,
+            Err(errs) => errs[0].message.starts_with("expression nesting too deep"),
+        };
+
+        std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(parse)
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_crash() {
+        let depth = 10_000;
+        assert!(too_deep(&format!(
+            "{}1{};",
+            "(".repeat(depth),
+            ")".repeat(depth)
+        )));
+        assert!(too_deep(&format!("{}1;", "-".repeat(depth))));
+        assert!(too_deep(&format!("{}1;", "x = ".repeat(depth))));
+        assert!(too_deep(&format!("1{};", " + 1".repeat(depth))));
+        assert!(too_deep(&format!("f{};", "()".repeat(depth))));
+        assert!(too_deep(&format!("a{};", "[0]".repeat(depth))));
+        assert!(too_deep(&format!("{}1;", "a ? b : ".repeat(depth))));
+    }
+
+    #[test]
+    fn max_depth_is_configurable() {
+        let tokens = Lexer::from_str("((1 + 2));").lex().unwrap();
+        assert!(
+            Parser::new(tokens.clone())
+                .with_max_depth(8)
+                .parse()
+                .is_ok()
+        );
+
+        let errs = Parser::new(tokens).with_max_depth(2).parse().unwrap_err();
+        assert_eq!(
+            errs[0].to_string(),
+            "expression nesting too deep at line: 1, col: 3"
+        );
+    }
+
+    #[test]
+    fn parses_named_arguments() {
+        assert_eq!(dump("move(x: 1, y: 2);"), ["move(x: 1, y: 2);"]);
+        assert_eq!(dump("move(1, 2);"), ["move(1, 2);"]);
+        assert_eq!(dump("move(1, y: 2);"), ["move(1, y: 2);"]);
+    }
+
+    #[test]
+    fn positional_argument_after_a_named_one_is_an_error() {
+        assert_eq!(
+            errors("move(x: 1, 2);")[0],
+            "positional argument after named argument at line: 1, col: 12"
+        );
+    }
+
+    #[test]
+    fn tells_tuples_from_groupings() {
+        let kinds: Vec<&str> = ["(1);", "(1,);", "(1, 2);"]
+            .iter()
+            .map(|src| match &parse(src).unwrap()[0] {
+                Statement::ExpressionStatement { expression, .. } => match expression {
+                    Expression::Grouping { .. } => "grouping",
+                    Expression::Tuple { .. } => "tuple",
+                    other => panic!("unexpected {:?}", other),
+                },
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(kinds, ["grouping", "tuple", "tuple"]);
+
+        assert_eq!(dump("(1,);"), ["(1,);"]);
+        assert_eq!(
+            dump("let t: (int, bool) = (1, true);"),
+            ["let t: (int, bool) = (1, true)"]
+        );
+    }
+
+    #[test]
+    fn calls_chain_on_any_expression() {
+        assert_eq!(dump("f()();"), ["f()();"]);
+        assert_eq!(dump("arr[0](1);"), ["arr[0](1);"]);
+        assert_eq!(dump("get_fn()(1, 2);"), ["get_fn()(1, 2);"]);
+    }
+
+    #[test]
+    fn void_is_the_unit_type() {
+        assert_eq!(
+            dump("fn g() -> void { return; }"),
+            ["fn g() -> void { return }"]
+        );
+    }
+
+    #[test]
+    fn duplicate_parameters_are_errors() {
+        assert_eq!(
+            errors("fn f(x: int, x: int) {}")[0],
+            "duplicate parameter 'x' at line: 1, col: 14"
+        );
+        assert!(parse("fn f(x: int, y: int) {}").is_ok());
+    }
+
+    #[test]
+    fn calls_display_like_source() {
+        assert_eq!(dump("f(1, 2);"), ["f(1, 2);"]);
+        assert_eq!(dump("f();"), ["f();"]);
+    }
+
+    #[test]
+    fn only_uncalled_block_expressions_skip_the_semicolon() {
+        assert_eq!(dump("fn f() {} 1;").len(), 2);
+        assert_eq!(dump("fn () { 1; }(); g();").len(), 2);
+        assert_eq!(
+            errors("fn () { 1; }() g();")[0],
+            "expected Semicolon at line: 1, col: 16"
+        );
+        assert_eq!(
+            errors("g() h();")[0],
+            "expected Semicolon at line: 1, col: 5"
+        );
+    }
+
+    #[test]
+    fn parses_optional_types() {
+        assert_eq!(dump("fn f(x: int?) {}"), ["fn f(x: int?) -> void {  }"]);
+
+        let stmts = parse("let x: int?? = y;").unwrap();
+        let Statement::Let {
+            type_annotation: Some(Type::Optional { inner }),
+            ..
+        } = &stmts[0]
+        else {
+            panic!("expected an optional type, got {:?}", stmts[0]);
+        };
+        assert!(matches!(&**inner, Type::Optional { inner } if matches!(**inner, Type::Int)));
+        assert_eq!(stmts[0].to_string(), "let x: int?? = y");
+    }
+
+    #[test]
+    fn operator_table_drives_binary_parsing() {
+        for (kind, (left, right)) in BINARY_OPERATORS {
+            assert!(Parser::is_binary_operator(*kind));
+            assert_eq!(Parser::get_binding_power(*kind), (*left, *right));
+
+            let symbol = kind.symbol().unwrap();
+            assert_eq!(
+                shape(&format!("a {} b;", symbol)),
+                format!("(a {} b)", symbol)
+            );
+        }
+
+        assert!(!Parser::is_binary_operator(TokenKind::Eq));
+        assert!(!Parser::is_binary_operator(TokenKind::Bang));
+        assert_eq!(Parser::get_binding_power(TokenKind::Id), (0, 0));
+    }
+
+    #[test]
+    fn index_expressions_are_assignable() {
+        assert_eq!(dump("a[1] = 2;"), ["a[1] = 2;"]);
+        assert_eq!(dump("a[0][1] += 2;"), ["a[0][1] += 2;"]);
+    }
+
+    #[test]
+    fn parses_labeled_loops() {
+        assert_eq!(
+            dump("outer: while (a) { for (;;) { break outer; } continue outer; }"),
+            ["outer: while (a) { for (; ; ) { break outer; } continue outer; }"]
+        );
+    }
+
+    #[test]
+    fn label_must_precede_a_loop() {
+        assert_eq!(
+            errors("outer: x = 1;")[0],
+            "label 'outer' must be followed by a loop at line: 1, col: 1"
+        );
+    }
+
+    #[test]
+    fn end_of_input_errors_name_the_last_token() {
+        assert_eq!(
+            errors("1 +"),
+            ["unexpected end of input after '+' at line: 1, col: 3"]
+        );
+        assert_eq!(
+            errors("let x ="),
+            ["unexpected end of input after '=' at line: 1, col: 7"]
+        );
+        assert_eq!(
+            errors("f(1,")[0],
+            "unexpected end of input after ',' at line: 1, col: 4"
+        );
+        assert_eq!(
+            errors("fn f() { -")[0],
+            "unexpected end of input after '-' at line: 1, col: 10"
+        );
+
+        let src = "fn f(a: int) -> int { let b = [a, -a][0]; return b > 1 ? f(a: b) : 0; }";
+        for end in 1..src.len() {
+            if let Err(errs) = parse(&src[..end]) {
+                assert!(
+                    errs.iter().all(|err| !err.message.is_empty()),
+                    "{}",
+                    &src[..end]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn grouping_displays_the_parentheses_written() {
+        assert_eq!(dump("((1 + 2));"), ["((1 + 2));"]);
+        assert_eq!(dump("(x);"), ["(x);"]);
+        assert_eq!(dump("-(1 + 2) * 3;"), ["-(1 + 2) * 3;"]);
+    }
+
+    #[test]
+    fn curr_is_and_peek_is_are_false_past_the_end() {
+        let tokens = Lexer::from_str("x").lex().unwrap();
+        let mut parser = Parser::from_tokens(tokens);
+
+        assert!(parser.curr_is(TokenKind::Id));
+        assert!(parser.peek_is(TokenKind::EOF));
+        assert!(!parser.peek_is(TokenKind::Id));
+
+        parser.advance();
+        assert!(parser.curr_is(TokenKind::EOF));
+        assert!(!parser.peek_is(TokenKind::EOF));
+
+        parser.advance();
+        assert!(!parser.curr_is(TokenKind::EOF));
+        assert!(!parser.peek_is(TokenKind::EOF));
     }
 }
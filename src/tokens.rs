@@ -1,14 +1,23 @@
 use std::fmt::{self, Display};
+use std::rc::Rc;
 
-#[derive(Clone, Debug)]
+use serde::Serialize;
+
+#[derive(Clone, Debug, Serialize)]
 pub struct Location {
-    pub col: usize,
     pub line: usize,
+    pub col: usize,
+    /// Absolute byte offset into the source.
+    pub offset: usize,
 }
 
 impl Location {
-    pub fn new(col: usize, line: usize) -> Self {
-        Self { col, line }
+    pub fn new(line: usize, col: usize) -> Self {
+        Self {
+            line,
+            col,
+            offset: 0,
+        }
     }
 
     pub fn add_line(&mut self) {
@@ -19,58 +28,304 @@ impl Location {
     pub fn add_col(&mut self) {
         self.col += 1;
     }
+
+    /// Steps over `ch` on the same line, moving the offset along with the
+    /// column.
+    pub fn add_char(&mut self, ch: char) {
+        self.col += 1;
+        self.offset += ch.len_utf8();
+    }
 }
 
+/// `line: X, col: Y` by default. The alternate form `{:#}` prints the
+/// `X:Y` editors expect after a file name.
 impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return write!(f, "{}:{}", self.line, self.col);
+        }
+
         write!(f, "line: {}, col: {}", self.line, self.col)
     }
 }
 
-#[derive(Clone, Debug)]
+/// The source range a syntax node covers, from the start of its first token
+/// to the end of its last one.
+#[derive(Clone, Debug, Serialize)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    pub fn new(start: Location, end: Location) -> Self {
+        Self { start, end }
+    }
+
+    pub fn to(&self, other: &Span) -> Span {
+        Span::new(self.start.clone(), other.end.clone())
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct Token {
     pub kind: TokenKind,
-    pub literal: String,
+    /// Shared between every occurrence of the same identifier or keyword,
+    /// see `Lexer::intern`.
+    pub literal: Rc<str>,
     pub location: Location,
+    pub end: Location,
 }
 
 impl Token {
-    pub fn new(kind: TokenKind, literal: String, location: Location) -> Self {
+    pub fn new(kind: TokenKind, literal: Rc<str>, location: Location, end: Location) -> Self {
         Self {
             kind,
             literal,
             location,
+            end,
+        }
+    }
+
+    pub fn start(&self) -> &Location {
+        &self.location
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.location.clone(), self.end.clone())
+    }
+
+    pub fn int_value(&self) -> Result<i64, String> {
+        let (digits, radix) = match self.literal.get(..2) {
+            Some("0x") => (&self.literal[2..], 16),
+            Some("0o") => (&self.literal[2..], 8),
+            Some("0b") => (&self.literal[2..], 2),
+            _ => (&*self.literal, 10),
+        };
+
+        i64::from_str_radix(&digits.replace('_', ""), radix).map_err(|_| {
+            format!(
+                "invalid integer literal '{}' at {}",
+                self.literal, self.location
+            )
+        })
+    }
+
+    pub fn unescaped(&self) -> Result<String, String> {
+        let quote = match self.kind {
+            TokenKind::String => '"',
+            TokenKind::Char => '\'',
+            _ => return Ok(self.literal.to_string()),
+        };
+
+        let inner = match self.literal.strip_prefix("\"\"\"") {
+            Some(rest) if quote == '"' && rest.len() >= 3 => {
+                rest.strip_suffix("\"\"\"").unwrap_or(rest)
+            }
+            _ => {
+                let inner = self.literal.strip_prefix(quote).unwrap_or(&self.literal);
+                inner.strip_suffix(quote).unwrap_or(inner)
+            }
+        };
+
+        let mut value = String::new();
+        let mut chars = inner.chars();
+
+        while let Some(ch) = chars.next() {
+            if ch != '\\' {
+                value.push(ch);
+                continue;
+            }
+
+            let decoded = match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('\\') => '\\',
+                Some('"') => '"',
+                Some('\'') => '\'',
+                Some('0') => '\0',
+                Some('x') => {
+                    let digits: String = chars.by_ref().take(2).collect();
+                    let escape = format!("\\x{}", digits);
+                    self.escaped_char(&escape, &digits, digits.len() == 2)?
+                }
+                Some('u') => {
+                    let rest = chars.as_str();
+                    let Some(digits) = rest
+                        .strip_prefix('{')
+                        .and_then(|braced| braced.find('}').map(|end| &braced[..end]))
+                    else {
+                        return Err(format!(
+                            "unicode escape must look like '\\u{{...}}' at {}",
+                            self.location
+                        ));
+                    };
+                    chars = rest[digits.len() + 2..].chars();
+
+                    let escape = format!("\\u{{{}}}", digits);
+                    self.escaped_char(&escape, digits, (1..=6).contains(&digits.len()))?
+                }
+                Some(other) => {
+                    return Err(format!("unknown escape sequence '\\{}'", other));
+                }
+                None => {
+                    return Err("incomplete escape sequence".into());
+                }
+            };
+            value.push(decoded);
         }
+
+        Ok(value)
+    }
+
+    /// Decodes the hex digits of a `\x` or `\u{...}` escape.
+    fn escaped_char(&self, escape: &str, digits: &str, valid_len: bool) -> Result<char, String> {
+        if !valid_len || !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return Err(format!(
+                "invalid escape sequence '{}' at {}",
+                escape, self.location
+            ));
+        }
+
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| {
+                format!(
+                    "escape sequence '{}' is not a valid character at {}",
+                    escape, self.location
+                )
+            })
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
 pub enum TokenKind {
     EOF,
     Plus,
     Minus,
     Star,
     Slash,
+    Percent,
+    Caret,
     Eq,
     Eq2,
-    Inc,  // ++
-    Decr, // decr
+    PlusEq,   // +=
+    MinusEq,  // -=
+    StarEq,   // *=
+    SlashEq,  // /=
+    Ne,       // !=
+    Lt,       // <
+    Gt,       // >
+    Le,       // <=
+    Ge,       // >=
+    AmpAmp,   // &&
+    PipePipe, // ||
+    Bang,     // !
+    Inc,      // ++
+    Decr,     // --
+    Arrow,    // ->
+    Question, // ?
     Colon,
     Semicolon,
-    LParen, //(
-    RParen, // )
-    LCurly, // {
-    RCurly, // }
+    LParen,   //(
+    RParen,   // )
+    LCurly,   // {
+    RCurly,   // }
+    LBracket, // [
+    RBracket, // ]
     Id,
     Int,
     Float,
     String,
+    Char,
     Comma,
+    Dot,
     Fn,
     Return,
+    True,
+    False,
+    Bool,
+    Let,
+    Const,
+    If,
+    Else,
+    While,
+    For,
+    Break,
+    Continue,
+    Struct,
+    Import,
+    Comment,
 }
 
+/// Every reserved word together with the kind it lexes to. New keywords only
+/// need an entry here for the lexer to pick them up.
+const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("return", TokenKind::Return),
+    ("fn", TokenKind::Fn),
+    ("int", TokenKind::Int),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
+    ("bool", TokenKind::Bool),
+    ("let", TokenKind::Let),
+    ("const", TokenKind::Const),
+    ("if", TokenKind::If),
+    ("else", TokenKind::Else),
+    ("while", TokenKind::While),
+    ("for", TokenKind::For),
+    ("break", TokenKind::Break),
+    ("continue", TokenKind::Continue),
+    ("struct", TokenKind::Struct),
+    ("import", TokenKind::Import),
+];
+
 impl TokenKind {
+    pub fn keywords() -> &'static [(&'static str, TokenKind)] {
+        KEYWORDS
+    }
+
+    pub fn keyword(word: &str) -> Option<TokenKind> {
+        KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == word)
+            .map(|(_, kind)| *kind)
+    }
+
+    pub fn is_keyword(word: &str) -> bool {
+        Self::keyword(word).is_some()
+    }
+
+    /// The source text of an operator, or `None` for every other kind.
+    pub fn symbol(&self) -> Option<&'static str> {
+        let s = match self {
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::Caret => "^",
+            TokenKind::Eq => "=",
+            TokenKind::Eq2 => "==",
+            TokenKind::PlusEq => "+=",
+            TokenKind::MinusEq => "-=",
+            TokenKind::StarEq => "*=",
+            TokenKind::SlashEq => "/=",
+            TokenKind::Ne => "!=",
+            TokenKind::Lt => "<",
+            TokenKind::Gt => ">",
+            TokenKind::Le => "<=",
+            TokenKind::Ge => ">=",
+            TokenKind::AmpAmp => "&&",
+            TokenKind::PipePipe => "||",
+            TokenKind::Bang => "!",
+            TokenKind::Inc => "++",
+            TokenKind::Decr => "--",
+            _ => return None,
+        };
+        Some(s)
+    }
+
     pub fn is_one_of(&self, kinds: &[TokenKind]) -> bool {
         kinds.contains(self)
     }
@@ -82,30 +337,167 @@ impl TokenKind {
 
 impl Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(s) = self.symbol() {
+            return f.write_str(s);
+        }
+
         let s = match self {
             TokenKind::EOF => "end of file",
-            TokenKind::Plus => "+",
-            TokenKind::Minus => "-",
-            TokenKind::Star => "*",
-            TokenKind::Slash => "/",
-            TokenKind::Eq => "=",
-            TokenKind::Eq2 => "==",
-            TokenKind::Inc => "++",
-            TokenKind::Decr => "decr",
+            TokenKind::Arrow => "->",
+            TokenKind::Question => "?",
             TokenKind::Colon => ":",
             TokenKind::Semicolon => ";",
             TokenKind::LParen => "(",
             TokenKind::RParen => ")",
             TokenKind::LCurly => "{",
             TokenKind::RCurly => "}",
+            TokenKind::LBracket => "[",
+            TokenKind::RBracket => "]",
             TokenKind::Id => "identifier",
             TokenKind::Int => "integer literal",
             TokenKind::Float => "float literal",
             TokenKind::String => "string literal",
+            TokenKind::Char => "character literal",
             TokenKind::Comma => ",",
+            TokenKind::Dot => ".",
             TokenKind::Fn => "fn",
             TokenKind::Return => "return",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::Bool => "bool",
+            TokenKind::Let => "let",
+            TokenKind::Const => "const",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+            TokenKind::While => "while",
+            TokenKind::For => "for",
+            TokenKind::Break => "break",
+            TokenKind::Continue => "continue",
+            TokenKind::Struct => "struct",
+            TokenKind::Import => "import",
+            TokenKind::Comment => "comment",
+            TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Star
+            | TokenKind::Slash
+            | TokenKind::Percent
+            | TokenKind::Caret
+            | TokenKind::Eq
+            | TokenKind::Eq2
+            | TokenKind::PlusEq
+            | TokenKind::MinusEq
+            | TokenKind::StarEq
+            | TokenKind::SlashEq
+            | TokenKind::Ne
+            | TokenKind::Lt
+            | TokenKind::Gt
+            | TokenKind::Le
+            | TokenKind::Ge
+            | TokenKind::AmpAmp
+            | TokenKind::PipePipe
+            | TokenKind::Bang
+            | TokenKind::Inc
+            | TokenKind::Decr => unreachable!("operators are displayed by their symbol"),
         };
-        write!(f, "{}", s)
+        f.write_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(literal: &str) -> Token {
+        let at = Location::new(1, 1);
+        Token::new(TokenKind::String, literal.into(), at.clone(), at)
+    }
+
+    #[test]
+    fn decodes_escape_sequences() {
+        assert_eq!(string(r#""a\tb""#).unescaped(), Ok("a\tb".to_string()));
+        assert_eq!(
+            string(r#""\n\\\"\0""#).unescaped(),
+            Ok("\n\\\"\0".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_escape_is_an_error() {
+        assert_eq!(
+            string(r#""\q""#).unescaped(),
+            Err(r"unknown escape sequence '\q'".to_string())
+        );
+    }
+
+    #[test]
+    fn location_formats_for_people_and_editors() {
+        let at = Location::new(3, 14);
+        assert_eq!(at.to_string(), "line: 3, col: 14");
+        assert_eq!(format!("{:#}", at), "3:14");
+    }
+
+    #[test]
+    fn new_takes_line_then_col() {
+        let at = Location::new(2, 7);
+        assert_eq!((at.line, at.col, at.offset), (2, 7, 0));
+    }
+
+    #[test]
+    fn symbol_is_only_defined_for_operators() {
+        let operators = [
+            (TokenKind::Plus, "+"),
+            (TokenKind::Minus, "-"),
+            (TokenKind::Star, "*"),
+            (TokenKind::Slash, "/"),
+            (TokenKind::Percent, "%"),
+            (TokenKind::Caret, "^"),
+            (TokenKind::Eq, "="),
+            (TokenKind::Eq2, "=="),
+            (TokenKind::PlusEq, "+="),
+            (TokenKind::MinusEq, "-="),
+            (TokenKind::StarEq, "*="),
+            (TokenKind::SlashEq, "/="),
+            (TokenKind::Ne, "!="),
+            (TokenKind::Lt, "<"),
+            (TokenKind::Gt, ">"),
+            (TokenKind::Le, "<="),
+            (TokenKind::Ge, ">="),
+            (TokenKind::AmpAmp, "&&"),
+            (TokenKind::PipePipe, "||"),
+            (TokenKind::Bang, "!"),
+            (TokenKind::Inc, "++"),
+            (TokenKind::Decr, "--"),
+        ];
+
+        for (kind, symbol) in operators {
+            assert_eq!(kind.symbol(), Some(symbol), "{:?}", kind);
+        }
+        assert_eq!(TokenKind::Id.symbol(), None);
+        assert_eq!(TokenKind::EOF.symbol(), None);
+    }
+
+    #[test]
+    fn decodes_hex_and_unicode_escapes() {
+        assert_eq!(string(r#""\x41""#).unescaped(), Ok("A".to_string()));
+        assert_eq!(string(r#""\u{1F600}""#).unescaped(), Ok("😀".to_string()));
+    }
+
+    #[test]
+    fn invalid_hex_and_unicode_escapes_are_errors() {
+        assert_eq!(
+            string(r#""\u{110000}""#).unescaped(),
+            Err(
+                "escape sequence '\\u{110000}' is not a valid character at line: 1, col: 1"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            string(r#""\xG1""#).unescaped(),
+            Err("invalid escape sequence '\\xG1' at line: 1, col: 1".to_string())
+        );
+        assert_eq!(
+            string(r#""\u{}""#).unescaped(),
+            Err("invalid escape sequence '\\u{}' at line: 1, col: 1".to_string())
+        );
     }
 }
@@ -0,0 +1,1130 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::args::order_args;
+use crate::ast::{Expression, Statement, Type, TypeMapping};
+use crate::env::Env;
+use crate::error::CompileError;
+use crate::tokens::{Location, Span, Token, TokenKind};
+use crate::visitors::visitor::{EVisitor, SVisitor};
+
+struct Binding {
+    t: Type,
+    constant: bool,
+    used: bool,
+    /// Parameters of the function the binding holds, when known, for
+    /// resolving named arguments.
+    params: Option<Vec<TypeMapping>>,
+    /// Where the named function this binding was made for is defined.
+    function: Option<Location>,
+}
+
+impl Binding {
+    fn var(t: Type) -> Self {
+        Self {
+            t,
+            constant: false,
+            used: false,
+            params: None,
+            function: None,
+        }
+    }
+}
+
+struct FunctionContext {
+    name: Option<Token>,
+    return_type: Type,
+}
+
+pub struct TypeChecker {
+    env: Env<Binding>,
+    functions: Vec<FunctionContext>,
+    structs: HashMap<String, Vec<TypeMapping>>,
+    /// Labels of the loops enclosing the statement being checked, within the
+    /// current function, innermost last. Unlabeled loops are `None`.
+    loops: Vec<Option<Rc<str>>>,
+    /// Diagnostics that do not fail the check.
+    warnings: Vec<String>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self {
+            env: Env::new(),
+            functions: Vec::new(),
+            structs: HashMap::new(),
+            loops: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn check(&mut self, stmts: &[Statement]) -> Result<(), Vec<CompileError>> {
+        let mut errs: Vec<CompileError> = Vec::new();
+
+        self.declare_functions(stmts);
+        for stmt in stmts {
+            if let Err(err) = SVisitor::visit(self, stmt) {
+                errs.push(err);
+            }
+        }
+
+        if !errs.is_empty() {
+            return Err(errs);
+        }
+
+        Ok(())
+    }
+
+    /// Defines the signature of every named top-level function up front so
+    /// that a body can call a function declared further down the file.
+    fn declare_functions(&mut self, stmts: &[Statement]) {
+        for stmt in stmts {
+            if let Statement::ExpressionStatement {
+                expression:
+                    Expression::FunctionLiteral {
+                        name: Some(name),
+                        params,
+                        return_type,
+                        ..
+                    },
+                ..
+            } = stmt
+            {
+                self.env.define(
+                    &name.literal,
+                    Binding {
+                        params: Some(params.to_vec()),
+                        ..Binding::var(Self::function_type(params, return_type))
+                    },
+                );
+            }
+        }
+    }
+
+    fn function_type(params: &[TypeMapping], return_type: &Option<Type>) -> Type {
+        Type::Function {
+            return_type: return_type.clone().unwrap_or(Type::Unit).into(),
+            param_types: params.iter().map(|p| p.t.clone()).collect(),
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expression) -> Result<Type, CompileError> {
+        EVisitor::visit(self, expr)
+    }
+
+    fn check_block(&mut self, stmts: &[Statement]) -> Result<Type, CompileError> {
+        self.warn_unreachable(stmts);
+
+        self.env.push_scope();
+        let result = stmts
+            .iter()
+            .try_for_each(|stmt| SVisitor::visit(self, stmt).map(|_| ()));
+        self.env.pop_scope();
+
+        result.map(|_| Type::Unit)
+    }
+
+    fn check_for(
+        &mut self,
+        label: Option<&Token>,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) -> Result<Type, CompileError> {
+        if let Some(init) = init {
+            SVisitor::visit(self, init)?;
+        }
+
+        if let Some(condition) = condition {
+            let cond = self.check_expr(condition)?;
+            Self::expect_type(&Type::Bool, &cond, "for condition", condition.location())?;
+        }
+
+        if let Some(step) = step {
+            self.check_expr(step)?;
+        }
+
+        self.check_loop_body(label, body)
+    }
+
+    fn check_loop_body(
+        &mut self,
+        label: Option<&Token>,
+        body: &[Statement],
+    ) -> Result<Type, CompileError> {
+        self.loops.push(label.map(|label| label.literal.clone()));
+        let result = self.check_block(body);
+        self.loops.pop();
+
+        result
+    }
+
+    /// Must run while the function's parameter scope is still the innermost.
+    /// Names starting with `_` are deliberately unused.
+    fn warn_unused_params(&mut self, params: &[TypeMapping]) {
+        for param in params {
+            if let Expression::Id { name, .. } = &param.expr
+                && !name.literal.starts_with('_')
+                && self.env.get(&name.literal).is_some_and(|b| !b.used)
+            {
+                self.warnings.push(format!(
+                    "unused parameter '{}' at line {}",
+                    name.literal, name.location.line
+                ));
+            }
+        }
+    }
+
+    /// Flags the first statement after a `return` in the same block. Returns
+    /// nested in branches are not followed.
+    fn warn_unreachable(&mut self, stmts: &[Statement]) {
+        let Some(i) = stmts
+            .iter()
+            .position(|stmt| matches!(stmt, Statement::Return { .. }))
+        else {
+            return;
+        };
+
+        let unreachable = stmts[i + 1..]
+            .iter()
+            .find(|stmt| !matches!(stmt, Statement::Empty { .. }));
+
+        if let Some(span) = unreachable.and_then(|stmt| stmt.span()) {
+            self.warnings.push(format!(
+                "unreachable statement after return at line {}",
+                span.start.line
+            ));
+        }
+    }
+
+    fn expect_in_loop(
+        &self,
+        keyword: &str,
+        label: Option<&Token>,
+        span: &Span,
+    ) -> Result<Type, CompileError> {
+        if self.loops.is_empty() {
+            return Err(CompileError::type_error(
+                format!("'{}' outside of a loop", keyword),
+                span.start.clone(),
+            ));
+        }
+
+        if let Some(label) = label
+            && !self.loops.iter().flatten().any(|l| *l == label.literal)
+        {
+            return Err(CompileError::type_error(
+                format!("undefined label '{}'", label.literal),
+                label.location.clone(),
+            ));
+        }
+
+        Ok(Type::Unit)
+    }
+
+    fn declare(
+        &mut self,
+        name: &Token,
+        type_annotation: Option<&Type>,
+        value: &Expression,
+        constant: bool,
+    ) -> Result<Type, CompileError> {
+        let actual = self.check_expr(value)?;
+        let params = self.params_of(value);
+
+        if let Some(expected) = type_annotation {
+            Self::expect_type(
+                expected,
+                &actual,
+                &format!("declaration of '{}'", name.literal),
+                &name.location,
+            )?;
+        }
+
+        self.env.define(
+            &name.literal,
+            Binding {
+                t: actual.clone(),
+                constant,
+                used: false,
+                params,
+                function: None,
+            },
+        );
+        Ok(actual)
+    }
+
+    /// Parameter names of the function `expr` evaluates to, when that is
+    /// known without running it: a function literal, or a variable bound
+    /// to one.
+    fn params_of(&self, expr: &Expression) -> Option<Vec<TypeMapping>> {
+        match expr.ungrouped() {
+            Expression::FunctionLiteral { params, .. } => Some(params.clone()),
+            Expression::Id { name, .. } => self.env.get(&name.literal)?.params.clone(),
+            _ => None,
+        }
+    }
+
+    fn check_builtin(
+        &mut self,
+        name: &Token,
+        args: &[Expression],
+        named_args: &[(Token, Expression)],
+    ) -> Option<Result<Type, CompileError>> {
+        let builtin = match &*name.literal {
+            "print" => Self::check_print,
+            "assert" => Self::check_assert,
+            _ => return None,
+        };
+
+        if let Some((arg, _)) = named_args.first() {
+            return Some(Err(CompileError::type_error(
+                format!("builtin '{}' does not take named arguments", name.literal),
+                arg.location.clone(),
+            )));
+        }
+
+        Some(builtin(self, name, args))
+    }
+
+    fn check_print(&mut self, name: &Token, args: &[Expression]) -> Result<Type, CompileError> {
+        let [arg] = args else {
+            return Err(CompileError::type_error(
+                format!("'print' expects 1 argument, but got {}", args.len()),
+                name.location.clone(),
+            ));
+        };
+
+        self.check_expr(arg)?;
+        Ok(Type::Unit)
+    }
+
+    fn check_assert(&mut self, name: &Token, args: &[Expression]) -> Result<Type, CompileError> {
+        let (condition, message) = match args {
+            [condition] => (condition, None),
+            [condition, message] => (condition, Some(message)),
+            _ => {
+                return Err(CompileError::type_error(
+                    format!("'assert' expects 1 or 2 arguments, but got {}", args.len()),
+                    name.location.clone(),
+                ));
+            }
+        };
+
+        let actual = self.check_expr(condition)?;
+        if actual != Type::Bool && actual != Type::Int {
+            return Err(CompileError::type_error(
+                format!("'assert' expects a bool or int condition, found {}", actual),
+                name.location.clone(),
+            ));
+        }
+        if let Some(message) = message {
+            let actual = self.check_expr(message)?;
+            Self::expect_type(&Type::String, &actual, "assert message", message.location())?;
+        }
+
+        Ok(Type::Unit)
+    }
+
+    fn binary_type(op: &Token, lhs: Type, rhs: Type) -> Result<Type, CompileError> {
+        let result = match op.kind {
+            TokenKind::Plus if lhs == Type::String && rhs == Type::String => Some(Type::String),
+            TokenKind::Star if lhs == Type::String && rhs == Type::Int => Some(Type::String),
+            TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Star
+            | TokenKind::Slash
+            | TokenKind::Percent
+            | TokenKind::Caret => match (&lhs, &rhs) {
+                (Type::Int, Type::Int) => Some(Type::Int),
+                _ => None,
+            },
+            TokenKind::Lt | TokenKind::Gt | TokenKind::Le | TokenKind::Ge => match (&lhs, &rhs) {
+                (Type::Int, Type::Int) => Some(Type::Bool),
+                _ => None,
+            },
+            TokenKind::Eq2 | TokenKind::Ne if lhs == rhs => Some(Type::Bool),
+            TokenKind::AmpAmp | TokenKind::PipePipe => match (&lhs, &rhs) {
+                (Type::Bool, Type::Bool) => Some(Type::Bool),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        result.ok_or_else(|| {
+            CompileError::type_error(
+                format!("cannot apply '{}' to {} and {}", op.kind, lhs, rhs),
+                op.location.clone(),
+            )
+        })
+    }
+
+    fn expect_type(
+        expected: &Type,
+        actual: &Type,
+        what: &str,
+        location: &Location,
+    ) -> Result<(), CompileError> {
+        if expected == actual {
+            return Ok(());
+        }
+
+        Err(CompileError::type_error(
+            format!(
+                "type mismatch in {}: expected {}, found {}",
+                what, expected, actual
+            ),
+            location.clone(),
+        ))
+    }
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SVisitor<Type, CompileError> for TypeChecker {
+    fn visit_return(
+        &mut self,
+        value: Option<&Expression>,
+        span: &Span,
+    ) -> Result<Type, CompileError> {
+        let actual = match value {
+            Some(expr) => self.check_expr(expr)?,
+            None => Type::Unit,
+        };
+
+        if let Some(function) = self.functions.last() {
+            let what = match &function.name {
+                Some(name) => format!("return of function '{}'", name.literal),
+                None => "return of anonymous function".to_string(),
+            };
+            Self::expect_type(
+                &function.return_type,
+                &actual,
+                &what,
+                // a bare `return;` has no value to point at, so use the statement
+                value.map_or(&span.start, |expr| expr.location()),
+            )?;
+        }
+
+        Ok(actual)
+    }
+
+    fn visit_let(
+        &mut self,
+        name: &Token,
+        type_annotation: Option<&Type>,
+        value: &Expression,
+    ) -> Result<Type, CompileError> {
+        self.declare(name, type_annotation, value, false)
+    }
+
+    fn visit_const(
+        &mut self,
+        name: &Token,
+        type_annotation: Option<&Type>,
+        value: &Expression,
+    ) -> Result<Type, CompileError> {
+        self.declare(name, type_annotation, value, true)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expression,
+        then_branch: &[Statement],
+        else_branch: Option<&[Statement]>,
+    ) -> Result<Type, CompileError> {
+        let cond = self.check_expr(condition)?;
+        Self::expect_type(&Type::Bool, &cond, "if condition", condition.location())?;
+
+        self.check_block(then_branch)?;
+        if let Some(else_branch) = else_branch {
+            self.check_block(else_branch)?;
+        }
+
+        Ok(Type::Unit)
+    }
+
+    fn visit_while(
+        &mut self,
+        label: Option<&Token>,
+        condition: &Expression,
+        body: &[Statement],
+    ) -> Result<Type, CompileError> {
+        let cond = self.check_expr(condition)?;
+        Self::expect_type(&Type::Bool, &cond, "while condition", condition.location())?;
+
+        self.check_loop_body(label, body)
+    }
+
+    fn visit_for(
+        &mut self,
+        label: Option<&Token>,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) -> Result<Type, CompileError> {
+        // the init binding is only visible inside the loop
+        self.env.push_scope();
+        let result = self.check_for(label, init, condition, step, body);
+        self.env.pop_scope();
+
+        result
+    }
+
+    fn visit_break(&mut self, label: Option<&Token>, span: &Span) -> Result<Type, CompileError> {
+        self.expect_in_loop("break", label, span)
+    }
+
+    fn visit_continue(&mut self, label: Option<&Token>, span: &Span) -> Result<Type, CompileError> {
+        self.expect_in_loop("continue", label, span)
+    }
+
+    fn visit_struct_decl(
+        &mut self,
+        name: &Token,
+        fields: &[TypeMapping],
+    ) -> Result<Type, CompileError> {
+        if self.structs.contains_key(&*name.literal) {
+            return Err(CompileError::type_error(
+                format!("struct '{}' is already defined", name.literal),
+                name.location.clone(),
+            ));
+        }
+
+        for (i, field) in fields.iter().enumerate() {
+            if let Expression::Id {
+                name: field_name, ..
+            } = &field.expr
+                && fields[..i]
+                    .iter()
+                    .any(|f| *field_name.literal == f.expr.to_string())
+            {
+                return Err(CompileError::type_error(
+                    format!(
+                        "duplicate field '{}' in struct '{}'",
+                        field_name.literal, name.literal
+                    ),
+                    field_name.location.clone(),
+                ));
+            }
+        }
+
+        self.structs
+            .insert(name.literal.to_string(), fields.to_vec());
+        Ok(Type::Unit)
+    }
+
+    fn visit_expression_stmt(&mut self, expr: &Expression) -> Result<Type, CompileError> {
+        self.check_expr(expr)
+    }
+
+    fn visit_import(&mut self, path: &Token) -> Result<Type, CompileError> {
+        Err(CompileError::import(
+            format!("unresolved import {}", path.literal),
+            path.location.clone(),
+        ))
+    }
+
+    fn visit_empty(&mut self) -> Result<Type, CompileError> {
+        Ok(Type::Unit)
+    }
+
+    fn visit_halt(&mut self) -> Result<Type, CompileError> {
+        Ok(Type::Unit)
+    }
+}
+
+impl EVisitor<Type, CompileError> for TypeChecker {
+    fn visit_binary(
+        &mut self,
+        lhs: &Expression,
+        op: &Token,
+        rhs: &Expression,
+    ) -> Result<Type, CompileError> {
+        let lhs = self.check_expr(lhs)?;
+        let rhs = self.check_expr(rhs)?;
+
+        Self::binary_type(op, lhs, rhs)
+    }
+
+    fn visit_unary(&mut self, op: &Token, expr: &Expression) -> Result<Type, CompileError> {
+        let t = self.check_expr(expr)?;
+
+        match (op.kind, &t) {
+            (TokenKind::Plus | TokenKind::Minus, Type::Int) => Ok(Type::Int),
+            (TokenKind::Bang, Type::Bool) => Ok(Type::Bool),
+            _ => Err(CompileError::type_error(
+                format!("cannot apply unary '{}' to {}", op.kind, t),
+                expr.location().clone(),
+            )),
+        }
+    }
+
+    fn visit_ternary(
+        &mut self,
+        condition: &Expression,
+        then_expr: &Expression,
+        else_expr: &Expression,
+    ) -> Result<Type, CompileError> {
+        let cond = self.check_expr(condition)?;
+        Self::expect_type(
+            &Type::Bool,
+            &cond,
+            "ternary condition",
+            condition.location(),
+        )?;
+
+        let then_type = self.check_expr(then_expr)?;
+        let else_type = self.check_expr(else_expr)?;
+        Self::expect_type(
+            &then_type,
+            &else_type,
+            "ternary branches",
+            else_expr.location(),
+        )?;
+
+        Ok(then_type)
+    }
+
+    fn visit_assign(
+        &mut self,
+        target: &Expression,
+        op: Option<&Token>,
+        value: &Expression,
+    ) -> Result<Type, CompileError> {
+        // storing into an element still modifies the variable holding the array
+        let mut root = target.ungrouped();
+        while let Expression::Index { target, .. } = root {
+            root = target.ungrouped();
+        }
+
+        if let Expression::Id { name, .. } = root
+            && self.env.get(&name.literal).is_some_and(|b| b.constant)
+        {
+            return Err(CompileError::type_error(
+                format!("cannot assign to constant '{}'", name.literal),
+                name.location.clone(),
+            ));
+        }
+
+        let expected = self.check_expr(target)?;
+        let mut actual = self.check_expr(value)?;
+        if let Some(op) = op {
+            actual = Self::binary_type(op, expected.clone(), actual)?;
+        }
+        Self::expect_type(&expected, &actual, "assignment", value.location())?;
+
+        Ok(expected)
+    }
+
+    fn visit_function_call(
+        &mut self,
+        callee: &Expression,
+        args: &[Expression],
+        named_args: &[(Token, Expression)],
+    ) -> Result<Type, CompileError> {
+        if let Expression::Id { name, .. } = callee.ungrouped()
+            && self.env.get(&name.literal).is_none()
+            && let Some(result) = self.check_builtin(name, args, named_args)
+        {
+            return result;
+        }
+
+        let callee_type = self.check_expr(callee)?;
+
+        let Type::Function {
+            return_type,
+            param_types,
+        } = callee_type
+        else {
+            return Err(CompileError::type_error(
+                format!("cannot call a value of type {}", callee_type),
+                callee.location().clone(),
+            ));
+        };
+
+        if !named_args.is_empty() {
+            let Some(params) = self.params_of(callee) else {
+                return Err(CompileError::type_error(
+                    "named arguments need a function with known parameter names".into(),
+                    callee.span().start.clone(),
+                ));
+            };
+
+            let named = named_args.iter().map(|(name, arg)| (name, arg)).collect();
+            let args = order_args(&params, args.iter().collect(), named, &callee.span().start)?;
+            for (expected, arg) in param_types.iter().zip(args) {
+                let actual = self.check_expr(arg)?;
+                Self::expect_type(expected, &actual, "function argument", arg.location())?;
+            }
+
+            return Ok(*return_type);
+        }
+
+        if param_types.len() != args.len() {
+            return Err(CompileError::type_error(
+                format!(
+                    "expected {} arguments, but got {}",
+                    param_types.len(),
+                    args.len()
+                ),
+                callee.location().clone(),
+            ));
+        }
+
+        for (expected, arg) in param_types.iter().zip(args) {
+            let actual = self.check_expr(arg)?;
+            Self::expect_type(expected, &actual, "function argument", arg.location())?;
+        }
+
+        Ok(*return_type)
+    }
+
+    fn visit_function_literal(
+        &mut self,
+        name: &Option<Token>,
+        params: &[TypeMapping],
+        return_type: &Option<Type>,
+        body: &[Statement],
+    ) -> Result<Type, CompileError> {
+        let function_type = Self::function_type(params, return_type);
+        let return_type = return_type.clone().unwrap_or(Type::Unit);
+
+        if let Some(name) = name {
+            if let Some(first) = self
+                .env
+                .get_local(&name.literal)
+                .and_then(|b| b.function.as_ref())
+            {
+                return Err(CompileError::type_error(
+                    format!(
+                        "function '{}' is already defined (first at line {}, redefined at line {})",
+                        name.literal, first.line, name.location.line
+                    ),
+                    name.location.clone(),
+                ));
+            }
+
+            self.env.define(
+                &name.literal,
+                Binding {
+                    function: Some(name.location.clone()),
+                    params: Some(params.to_vec()),
+                    ..Binding::var(function_type.clone())
+                },
+            );
+        }
+
+        self.env.push_scope();
+        for param in params {
+            if let Expression::Id { name, .. } = &param.expr {
+                self.env
+                    .define(&name.literal, Binding::var(param.t.clone()));
+            }
+        }
+
+        self.functions.push(FunctionContext {
+            name: name.clone(),
+            return_type,
+        });
+        // loops around the literal do not reach into its body
+        let loops = std::mem::take(&mut self.loops);
+        let result = self.check_block(body);
+        self.loops = loops;
+        self.functions.pop();
+        self.warn_unused_params(params);
+        self.env.pop_scope();
+
+        result.map(|_| function_type)
+    }
+
+    fn visit_array(&mut self, elements: &[Expression], span: &Span) -> Result<Type, CompileError> {
+        let Some((first, rest)) = elements.split_first() else {
+            return Err(CompileError::type_error(
+                "cannot infer the element type of an empty array".into(),
+                span.start.clone(),
+            ));
+        };
+
+        let element = self.check_expr(first)?;
+        for e in rest {
+            let actual = self.check_expr(e)?;
+            Self::expect_type(&element, &actual, "array element", e.location())?;
+        }
+
+        Ok(Type::Array {
+            element: element.into(),
+        })
+    }
+
+    fn visit_grouping(&mut self, inner: &Expression) -> Result<Type, CompileError> {
+        self.check_expr(inner)
+    }
+
+    fn visit_tuple(&mut self, elements: &[Expression]) -> Result<Type, CompileError> {
+        let elements = elements
+            .iter()
+            .map(|e| self.check_expr(e))
+            .collect::<Result<Vec<Type>, CompileError>>()?;
+
+        Ok(Type::Tuple { elements })
+    }
+
+    fn visit_index(
+        &mut self,
+        target: &Expression,
+        index: &Expression,
+    ) -> Result<Type, CompileError> {
+        let target_type = self.check_expr(target)?;
+        let index_type = self.check_expr(index)?;
+        Self::expect_type(&Type::Int, &index_type, "array index", index.location())?;
+
+        match target_type {
+            Type::Array { element } => Ok(*element),
+            t => Err(CompileError::type_error(
+                format!("cannot index a value of type {}", t),
+                target.location().clone(),
+            )),
+        }
+    }
+
+    fn visit_member(&mut self, target: &Expression, field: &Token) -> Result<Type, CompileError> {
+        let target_type = self.check_expr(target)?;
+
+        if let Type::Named { name } = &target_type
+            && let Some(fields) = self.structs.get(name)
+            && let Some(mapping) = fields.iter().find(|f| *field.literal == f.expr.to_string())
+        {
+            return Ok(mapping.t.clone());
+        }
+
+        Err(CompileError::type_error(
+            format!("{} has no field '{}'", target_type, field.literal),
+            field.location.clone(),
+        ))
+    }
+
+    fn visit_struct_literal(
+        &mut self,
+        name: &Token,
+        fields: &[(Token, Expression)],
+    ) -> Result<Type, CompileError> {
+        let Some(declared) = self.structs.get(&*name.literal).cloned() else {
+            return Err(CompileError::type_error(
+                format!("undefined struct '{}'", name.literal),
+                name.location.clone(),
+            ));
+        };
+
+        for (i, (field, value)) in fields.iter().enumerate() {
+            if fields[..i].iter().any(|(f, _)| f.literal == field.literal) {
+                return Err(CompileError::type_error(
+                    format!("field '{}' is set more than once", field.literal),
+                    field.location.clone(),
+                ));
+            }
+
+            let Some(mapping) = declared
+                .iter()
+                .find(|f| *field.literal == f.expr.to_string())
+            else {
+                return Err(CompileError::type_error(
+                    format!("struct '{}' has no field '{}'", name.literal, field.literal),
+                    field.location.clone(),
+                ));
+            };
+
+            let actual = self.check_expr(value)?;
+            Self::expect_type(
+                &mapping.t,
+                &actual,
+                &format!("field '{}'", field.literal),
+                &field.location,
+            )?;
+        }
+
+        if let Some(missing) = declared.iter().find(|f| {
+            !fields
+                .iter()
+                .any(|(field, _)| *field.literal == f.expr.to_string())
+        }) {
+            return Err(CompileError::type_error(
+                format!(
+                    "missing field '{}' in '{}' literal",
+                    missing.expr, name.literal
+                ),
+                name.location.clone(),
+            ));
+        }
+
+        Ok(Type::Named {
+            name: name.literal.to_string(),
+        })
+    }
+
+    fn visit_id(&mut self, name: Token) -> Result<Type, CompileError> {
+        self.env
+            .get_mut(&name.literal)
+            .map(|b| {
+                b.used = true;
+                b.t.clone()
+            })
+            .ok_or_else(|| {
+                CompileError::type_error(
+                    format!("undefined identifier '{}'", name.literal),
+                    name.location.clone(),
+                )
+            })
+    }
+
+    fn visit_int(&mut self, _value: Token) -> Result<Type, CompileError> {
+        Ok(Type::Int)
+    }
+
+    fn visit_float(&mut self, value: Token) -> Result<Type, CompileError> {
+        Err(CompileError::type_error(
+            "float literals are not supported by the type checker yet".into(),
+            value.location,
+        ))
+    }
+
+    fn visit_str(&mut self, _value: Token) -> Result<Type, CompileError> {
+        Ok(Type::String)
+    }
+
+    fn visit_char(&mut self, value: Token) -> Result<Type, CompileError> {
+        Err(CompileError::type_error(
+            "character literals are not supported by the type checker yet".into(),
+            value.location,
+        ))
+    }
+
+    fn visit_bool(&mut self, _value: Token) -> Result<Type, CompileError> {
+        Ok(Type::Bool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(src: &str) -> Result<(), Vec<String>> {
+        let tokens = Lexer::from_str(src).lex().expect("source should lex");
+        let stmts = Parser::new(tokens).parse().expect("source should parse");
+
+        TypeChecker::new()
+            .check(&stmts)
+            .map_err(|errs| errs.iter().map(|err| err.to_string()).collect())
+    }
+
+    fn warnings(src: &str) -> Vec<String> {
+        let tokens = Lexer::from_str(src).lex().expect("source should lex");
+        let stmts = Parser::new(tokens).parse().expect("source should parse");
+        let mut checker = TypeChecker::new();
+        checker.check(&stmts).expect("source should typecheck");
+        checker.warnings().to_vec()
+    }
+
+    #[test]
+    fn accepts_a_well_typed_function() {
+        assert_eq!(
+            check("fn add(a: int, b: int) :: int { let c = a + b; return c; }"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_a_mismatched_return_type() {
+        assert_eq!(
+            check("fn f() :: int { return true; }").unwrap_err(),
+            [
+                "type mismatch in return of function 'f': expected int, found bool at line: 1, col: 24"
+            ]
+        );
+        assert_eq!(
+            check(r#"1 + "x";"#).unwrap_err(),
+            ["cannot apply '+' to int and string at line: 1, col: 3"]
+        );
+    }
+
+    #[test]
+    fn calls_a_function_declared_later() {
+        assert_eq!(
+            check("fn a() :: int { return b(); } fn b() :: int { return 1; }"),
+            Ok(())
+        );
+        assert_eq!(
+            check(
+                "fn even(n: int) :: bool { if (n < 1) { return true; } return odd(n - 1); } \
+                 fn odd(n: int) :: bool { if (n < 1) { return false; } return even(n - 1); }"
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn functions_resolve_names_where_they_are_defined() {
+        let src =
+            "fn outer() -> int { let a = 1; let f = fn () -> int { return a; }; return f(); }";
+        assert_eq!(check(src), Ok(()));
+
+        let src = "let g = fn () -> int { return b; }; fn h() -> int { let b = 5; return g(); }";
+        assert_eq!(
+            check(src).unwrap_err(),
+            [
+                "undefined identifier 'b' at line: 1, col: 31",
+                "undefined identifier 'g' at line: 1, col: 71"
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_struct_literals_and_fields() {
+        let decl = "struct Point { x: int, y: int } ";
+        assert_eq!(
+            check(&format!("{decl}let p = Point {{ x: 1, y: 2 }}; p.x + p.y;")),
+            Ok(())
+        );
+        assert_eq!(
+            check(&format!("{decl}let p = Point {{ x: 1, y: true }};")).unwrap_err(),
+            ["type mismatch in field 'y': expected int, found bool at line: 1, col: 55"]
+        );
+        assert_eq!(
+            check(&format!("{decl}let p = Point {{ x: 1, y: 2 }}; p.z;")).unwrap_err(),
+            ["Point has no field 'z' at line: 1, col: 65"]
+        );
+    }
+
+    #[test]
+    fn rejects_reassigning_a_const() {
+        assert_eq!(
+            check("const PI: int = 3; PI = 4;").unwrap_err(),
+            ["cannot assign to constant 'PI' at line: 1, col: 20"]
+        );
+    }
+
+    #[test]
+    fn rejects_break_and_continue_outside_loops() {
+        assert_eq!(
+            check("break;").unwrap_err(),
+            ["'break' outside of a loop at line: 1, col: 1"]
+        );
+        assert_eq!(
+            check("while (true) { fn f() { continue; } }").unwrap_err(),
+            ["'continue' outside of a loop at line: 1, col: 25"]
+        );
+    }
+
+    #[test]
+    fn warns_about_unused_parameters() {
+        assert_eq!(
+            warnings("fn f(used: int, unused: int) -> int { return used; }"),
+            ["unused parameter 'unused' at line 1"]
+        );
+    }
+
+    #[test]
+    fn named_arguments_follow_the_function_in_scope() {
+        let src = "fn f(a: int) -> int { return a; } fn g() -> int { let f = fn (b: int) -> int { return b; }; return f(b: 1); } f(a: 2);";
+        assert_eq!(check(src), Ok(()));
+
+        assert_eq!(
+            check("fn f(a: int) -> int { return a; } f(b: 1);").unwrap_err(),
+            ["unknown parameter 'b' at line: 1, col: 37"]
+        );
+    }
+
+    #[test]
+    fn type_errors_carry_their_kind_and_location() {
+        let tokens = Lexer::from_str("let a = 1;\nlet b: bool = a;")
+            .lex()
+            .unwrap();
+        let stmts = Parser::new(tokens).parse().unwrap();
+        let errs = TypeChecker::new().check(&stmts).unwrap_err();
+
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].kind, ErrorKind::Type);
+        assert_eq!((errs[0].location.line, errs[0].location.col), (2, 5));
+    }
+
+    #[test]
+    fn checks_void_returns() {
+        assert_eq!(
+            check("fn f() { return; } fn g() -> void { return; }"),
+            Ok(())
+        );
+        assert_eq!(
+            check("fn f() -> int { return; }").unwrap_err(),
+            [
+                "type mismatch in return of function 'f': expected int, found void at line: 1, col: 17"
+            ]
+        );
+        assert_eq!(
+            check("fn f() { return 1; }").unwrap_err(),
+            [
+                "type mismatch in return of function 'f': expected void, found int at line: 1, col: 17"
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_code_after_return() {
+        assert_eq!(
+            warnings("fn f() -> int { return 1; let x = 2; }"),
+            ["unreachable statement after return at line 1"]
+        );
+        assert_eq!(
+            warnings("fn f(a: bool) -> int { if (a) { return 1; } return 2; }"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn rejects_jumps_to_undefined_labels() {
+        assert_eq!(check("outer: while (true) { break outer; }"), Ok(()));
+        assert_eq!(
+            check("outer: while (true) { break inner; }").unwrap_err(),
+            ["undefined label 'inner' at line: 1, col: 29"]
+        );
+    }
+
+    #[test]
+    fn checks_assert_arguments() {
+        assert_eq!(check(r#"assert(true, "msg");"#), Ok(()));
+        assert_eq!(
+            check("assert();").unwrap_err(),
+            ["'assert' expects 1 or 2 arguments, but got 0 at line: 1, col: 1"]
+        );
+    }
+
+    #[test]
+    fn rejects_redefining_a_function_in_the_same_scope() {
+        assert_eq!(
+            check("fn f() {}\nfn f() {}").unwrap_err(),
+            [
+                "function 'f' is already defined (first at line 1, redefined at line 2) at line: 2, col: 4"
+            ]
+        );
+    }
+
+    #[test]
+    fn allows_same_named_functions_in_nested_scopes() {
+        assert_eq!(
+            check("fn f() { fn g() {} } fn h() { fn g() {} } fn k() { fn k() {} }"),
+            Ok(())
+        );
+    }
+}
@@ -1 +1,2 @@
 pub mod visitor;
+pub mod walk;
@@ -1,47 +1,158 @@
 use crate::ast::{Statement, Expression, TypeMapping, Type};
-use crate::tokens::{TokenKind, Token};
+use crate::tokens::{Token, Span};
 
-pub trait SVisitor<R> {
-    fn visit(&mut self, stmt: &Statement) -> Result<R, String> {
+pub trait SVisitor<R, E = String> {
+    fn visit(&mut self, stmt: &Statement) -> Result<R, E> {
         match stmt {
-            Statement::Return { value } => self.visit_return(value.as_ref()),
-            Statement::ExpressionStatement { expression } => self.visit_expression_stmt(expression),
+            Statement::Return { value, span } => self.visit_return(value.as_ref(), span),
+            Statement::Let {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => self.visit_let(name, type_annotation.as_ref(), value),
+            Statement::Const {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => self.visit_const(name, type_annotation.as_ref(), value),
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => self.visit_if(condition, then_branch, else_branch.as_deref()),
+            Statement::While {
+                label,
+                condition,
+                body,
+                ..
+            } => self.visit_while(label.as_ref(), condition, body),
+            Statement::For {
+                label,
+                init,
+                condition,
+                step,
+                body,
+                ..
+            } => self.visit_for(label.as_ref(), init.as_deref(), condition.as_ref(), step.as_ref(), body),
+            Statement::Break { label, span } => self.visit_break(label.as_ref(), span),
+            Statement::Empty { .. } => self.visit_empty(),
+            Statement::Continue { label, span } => self.visit_continue(label.as_ref(), span),
+            Statement::StructDecl { name, fields, .. } => self.visit_struct_decl(name, fields),
+            Statement::ExpressionStatement { expression, .. } => self.visit_expression_stmt(expression),
+            Statement::Import { path, .. } => self.visit_import(path),
             Statement::Halt => self.visit_halt(),
         }
     }
 
-    fn visit_return(&mut self, value: Option<&Expression>) -> Result<R, String>;
-    fn visit_expression_stmt(&mut self, expr: &Expression) -> Result<R, String>;
-    fn visit_halt(&mut self) -> Result<R, String>;
+    fn visit_return(&mut self, value: Option<&Expression>, span: &Span) -> Result<R, E>;
+    fn visit_let(&mut self, name: &Token, type_annotation: Option<&Type>, value: &Expression) -> Result<R, E>;
+    fn visit_const(&mut self, name: &Token, type_annotation: Option<&Type>, value: &Expression) -> Result<R, E>;
+    fn visit_if(
+        &mut self,
+        condition: &Expression,
+        then_branch: &[Statement],
+        else_branch: Option<&[Statement]>,
+    ) -> Result<R, E>;
+    fn visit_while(&mut self, label: Option<&Token>, condition: &Expression, body: &[Statement]) -> Result<R, E>;
+    fn visit_for(
+        &mut self,
+        label: Option<&Token>,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        step: Option<&Expression>,
+        body: &[Statement],
+    ) -> Result<R, E>;
+    fn visit_break(&mut self, label: Option<&Token>, span: &Span) -> Result<R, E>;
+    fn visit_continue(&mut self, label: Option<&Token>, span: &Span) -> Result<R, E>;
+    fn visit_struct_decl(&mut self, name: &Token, fields: &[TypeMapping]) -> Result<R, E>;
+    fn visit_expression_stmt(&mut self, expr: &Expression) -> Result<R, E>;
+    fn visit_import(&mut self, path: &Token) -> Result<R, E>;
+    fn visit_empty(&mut self) -> Result<R, E>;
+    fn visit_halt(&mut self) -> Result<R, E>;
 }
 
-pub trait EVisitor<R> {
-    fn visit(&mut self, expr: &Expression) -> Result<R, String> {
+pub trait EVisitor<R, E = String> {
+    fn visit(&mut self, expr: &Expression) -> Result<R, E> {
         match expr {
-            Expression::Binary { lhs, op, rhs } => self.visit_binary(lhs, op, rhs),
-            Expression::Unary { op, expr } => self.visit_unary(op, expr),
-            Expression::FunctionCall { callee, args } => self.visit_function_call(callee, args),
+            Expression::Binary { lhs, op, rhs, .. } => self.visit_binary(lhs, op, rhs),
+            Expression::Unary { op, expr, .. } => self.visit_unary(op, expr),
+            Expression::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => self.visit_ternary(condition, then_expr, else_expr),
+            Expression::Assign {
+                target, op, value, ..
+            } => self.visit_assign(target, op.as_ref(), value),
+            Expression::FunctionCall {
+                callee,
+                args,
+                named_args,
+                ..
+            } => self.visit_function_call(callee, args, named_args),
             Expression::FunctionLiteral {
                 name,
                 params,
                 return_type,
                 body,
-            } => self.visit_function_literal(name, &params, return_type, &body),
-            Expression::Id { name } => self.visit_id(name.clone()),
-            Expression::Int { value } => self.visit_int(value.clone()),
+                ..
+            } => self.visit_function_literal(name, params, return_type, body),
+            Expression::Array { elements, span } => self.visit_array(elements, span),
+            Expression::Grouping { inner, .. } => self.visit_grouping(inner),
+            Expression::Tuple { elements, .. } => self.visit_tuple(elements),
+            Expression::Index { target, index, .. } => self.visit_index(target, index),
+            Expression::Member { target, field, .. } => self.visit_member(target, field),
+            Expression::StructLiteral { name, fields, .. } => self.visit_struct_literal(name, fields),
+            Expression::Id { name, .. } => self.visit_id(name.clone()),
+            Expression::Int { value, .. } => self.visit_int(value.clone()),
+            Expression::Float { value, .. } => self.visit_float(value.clone()),
+            Expression::Str { value, .. } => self.visit_str(value.clone()),
+            Expression::Char { value, .. } => self.visit_char(value.clone()),
+            Expression::Bool { value, .. } => self.visit_bool(value.clone()),
         }
     }
 
-    fn visit_binary(&mut self, lhs: &Expression, op: &TokenKind, rhs: &Expression) -> Result<R, String>;
-    fn visit_unary(&mut self, op: &TokenKind, expr: &Expression) -> Result<R, String>;
-    fn visit_function_call(&mut self, callee: &Expression, args: &[Expression]) -> Result<R, String>;
+    fn visit_binary(&mut self, lhs: &Expression, op: &Token, rhs: &Expression) -> Result<R, E>;
+    fn visit_unary(&mut self, op: &Token, expr: &Expression) -> Result<R, E>;
+    fn visit_ternary(
+        &mut self,
+        condition: &Expression,
+        then_expr: &Expression,
+        else_expr: &Expression,
+    ) -> Result<R, E>;
+    fn visit_assign(
+        &mut self,
+        target: &Expression,
+        op: Option<&Token>,
+        value: &Expression,
+    ) -> Result<R, E>;
+    fn visit_function_call(
+        &mut self,
+        callee: &Expression,
+        args: &[Expression],
+        named_args: &[(Token, Expression)],
+    ) -> Result<R, E>;
     fn visit_function_literal(
         &mut self, 
         name: &Option<Token>,
         params: &[TypeMapping],
         return_type: &Option<Type>,
         body: &[Statement]
-        ) -> Result<R, String>;
-    fn visit_id(&mut self, name: Token) -> Result<R, String>;
-    fn visit_int(&mut self, value: Token) -> Result<R, String>;
+        ) -> Result<R, E>;
+    fn visit_array(&mut self, elements: &[Expression], span: &Span) -> Result<R, E>;
+    fn visit_grouping(&mut self, inner: &Expression) -> Result<R, E>;
+    fn visit_tuple(&mut self, elements: &[Expression]) -> Result<R, E>;
+    fn visit_index(&mut self, target: &Expression, index: &Expression) -> Result<R, E>;
+    fn visit_member(&mut self, target: &Expression, field: &Token) -> Result<R, E>;
+    fn visit_struct_literal(&mut self, name: &Token, fields: &[(Token, Expression)]) -> Result<R, E>;
+    fn visit_id(&mut self, name: Token) -> Result<R, E>;
+    fn visit_int(&mut self, value: Token) -> Result<R, E>;
+    fn visit_float(&mut self, value: Token) -> Result<R, E>;
+    fn visit_str(&mut self, value: Token) -> Result<R, E>;
+    fn visit_char(&mut self, value: Token) -> Result<R, E>;
+    fn visit_bool(&mut self, value: Token) -> Result<R, E>;
 }
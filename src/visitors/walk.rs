@@ -0,0 +1,170 @@
+use crate::ast::{Expression, Statement};
+
+/// A read-only pass over the tree. Every method recurses into the node's
+/// children by default, so an implementor only overrides the nodes it cares
+/// about and calls `walk_stmt`/`walk_expr` to keep descending.
+pub trait Walk {
+    fn visit_stmt(&mut self, stmt: &Statement) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expression) {
+        walk_expr(self, expr);
+    }
+}
+
+/// Visits every statement in order.
+pub fn walk_stmts<W: Walk + ?Sized>(walker: &mut W, stmts: &[Statement]) {
+    for stmt in stmts {
+        walker.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<W: Walk + ?Sized>(walker: &mut W, stmt: &Statement) {
+    match stmt {
+        Statement::Return { value, .. } => {
+            if let Some(value) = value {
+                walker.visit_expr(value);
+            }
+        }
+        Statement::Let { value, .. } | Statement::Const { value, .. } => walker.visit_expr(value),
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            walker.visit_expr(condition);
+            walk_stmts(walker, then_branch);
+            if let Some(else_branch) = else_branch {
+                walk_stmts(walker, else_branch);
+            }
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            walker.visit_expr(condition);
+            walk_stmts(walker, body);
+        }
+        Statement::For {
+            init,
+            condition,
+            step,
+            body,
+            ..
+        } => {
+            if let Some(init) = init {
+                walker.visit_stmt(init);
+            }
+            if let Some(condition) = condition {
+                walker.visit_expr(condition);
+            }
+            if let Some(step) = step {
+                walker.visit_expr(step);
+            }
+            walk_stmts(walker, body);
+        }
+        Statement::ExpressionStatement { expression, .. } => walker.visit_expr(expression),
+        Statement::Break { .. }
+        | Statement::Continue { .. }
+        | Statement::Empty { .. }
+        | Statement::StructDecl { .. }
+        | Statement::Import { .. }
+        | Statement::Halt => {}
+    }
+}
+
+pub fn walk_expr<W: Walk + ?Sized>(walker: &mut W, expr: &Expression) {
+    match expr {
+        Expression::Binary { lhs, rhs, .. } => {
+            walker.visit_expr(lhs);
+            walker.visit_expr(rhs);
+        }
+        Expression::Unary { expr, .. } => walker.visit_expr(expr),
+        Expression::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            walker.visit_expr(condition);
+            walker.visit_expr(then_expr);
+            walker.visit_expr(else_expr);
+        }
+        Expression::Assign { target, value, .. } => {
+            walker.visit_expr(target);
+            walker.visit_expr(value);
+        }
+        Expression::FunctionCall {
+            callee,
+            args,
+            named_args,
+            ..
+        } => {
+            walker.visit_expr(callee);
+            for arg in args {
+                walker.visit_expr(arg);
+            }
+            for (_, arg) in named_args {
+                walker.visit_expr(arg);
+            }
+        }
+        // parameters are declarations, not uses, so only the body is walked
+        Expression::FunctionLiteral { body, .. } => walk_stmts(walker, body),
+        Expression::Array { elements, .. } | Expression::Tuple { elements, .. } => {
+            for element in elements {
+                walker.visit_expr(element);
+            }
+        }
+        Expression::Grouping { inner, .. } => walker.visit_expr(inner),
+        Expression::Index { target, index, .. } => {
+            walker.visit_expr(target);
+            walker.visit_expr(index);
+        }
+        Expression::Member { target, .. } => walker.visit_expr(target),
+        Expression::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                walker.visit_expr(value);
+            }
+        }
+        Expression::Id { .. }
+        | Expression::Int { .. }
+        | Expression::Float { .. }
+        | Expression::Str { .. }
+        | Expression::Char { .. }
+        | Expression::Bool { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[derive(Default)]
+    struct CallCounter {
+        calls: usize,
+    }
+
+    impl Walk for CallCounter {
+        fn visit_expr(&mut self, expr: &Expression) {
+            if let Expression::FunctionCall { .. } = expr {
+                self.calls += 1;
+            }
+
+            walk_expr(self, expr);
+        }
+    }
+
+    #[test]
+    fn counts_every_function_call() {
+        let src = "fn f(a: int) -> int { if (g(a)) { return h(a)(1); } return [k()][0]; } while (p()) { f(q(1)); }";
+        let tokens = Lexer::from_str(src).lex().unwrap();
+        let stmts = Parser::new(tokens).parse().unwrap();
+
+        let mut counter = CallCounter::default();
+        walk_stmts(&mut counter, &stmts);
+        assert_eq!(counter.calls, 7);
+    }
+}
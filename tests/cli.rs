@@ -0,0 +1,288 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs the `ice` binary with `args`, feeding it `stdin`.
+fn ice(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ice"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start ice");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn repl_prints_each_statement_and_continues_open_blocks() {
+    let output = ice(&[], "1 + 2;\nif (x) {\n  y;\n}\n");
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        ">> stmt: 1 + 2;\n>> .. .. stmt: if (x) { y; }\n>> \n"
+    );
+}
+
+#[test]
+fn reads_the_program_from_stdin() {
+    let output = ice(&["-"], "fn f() { return 1; }\nlet x = f();\n");
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "stmt: fn f() -> void { return 1 }\nstmt: let x = f()\n"
+    );
+}
+
+#[test]
+fn print_writes_to_stdout() {
+    let output = ice(&["--eval", "-"], "print(6 * 7);\n");
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "42\n");
+}
+
+/// Writes `src` to a fresh file in the temp directory and returns its path.
+fn source_file(name: &str, src: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("ice-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    std::fs::write(&path, src).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn tokens_flag_lists_the_tokens_in_order() {
+    let file = source_file("tokens.ic", "let x = 1;");
+    let output = ice(&["--tokens", &file], "");
+
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("Let \"let\" at line: 1, col: 1\n"));
+    let kinds: Vec<String> = stdout(&output)
+        .lines()
+        .map(|line| line.split(' ').next().unwrap().to_string())
+        .collect();
+    assert_eq!(kinds, ["Let", "Id", "Eq", "Int", "Semicolon", "EOF"]);
+}
+
+#[test]
+fn ast_json_tags_every_node() {
+    let output = ice(&["--ast-json", "-"], "let x = 1 + 2;");
+    assert!(output.status.success());
+
+    let tree: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let value = &tree[0]["Let"]["value"]["Binary"];
+    assert_eq!(tree[0]["Let"]["name"]["literal"], "x");
+    assert_eq!(value["op"]["kind"], "Plus");
+    assert_eq!(value["lhs"]["Int"]["value"]["location"]["col"], 9);
+    assert_eq!(value["rhs"]["Int"]["value"]["literal"], "2");
+}
+
+#[test]
+fn eval_prints_each_expression_value() {
+    let output = ice(&["--eval", "-"], "2 + 3 * 4;\nlet x = 1;\nx == 1;\n");
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "14\ntrue\n");
+}
+
+#[test]
+fn eval_reports_runtime_errors() {
+    let output = ice(&["--eval", "-"], "1 / 0;\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "runtime error: division by zero at line: 1, col: 3\n"
+    );
+}
+
+#[test]
+fn check_prints_warnings_without_failing() {
+    let output = ice(&["--check", "-"], "fn f(a: int) -> int { return 1; }\n");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "warning: unused parameter 'a' at line 1\n"
+    );
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn check_fails_on_a_type_mismatch() {
+    let output = ice(&["--check", "-"], "let x: int = true;\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        concat!(
+            "type error in <stdin>:1:5: type mismatch in declaration of 'x': ",
+            "expected int, found bool\n",
+            "  |\n",
+            "1 | let x: int = true;\n",
+            "  |     ^\n",
+        )
+    );
+}
+
+#[test]
+fn check_reports_every_kind_of_error_in_source_order() {
+    let output = ice(
+        &["--check", "-"],
+        "let x: int = true;\nlet = 2;\nlet y = $;\n",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    let headers: Vec<String> = stderr(&output)
+        .lines()
+        .filter(|line| line.contains(" error in "))
+        .map(|line| line.split(": ").next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            "type error in <stdin>:1:5",
+            "syntax error in <stdin>:2:5",
+            "lexical error in <stdin>:3:9",
+            "syntax error in <stdin>:3:10",
+        ]
+    );
+}
+
+#[test]
+fn check_passes_a_well_typed_program() {
+    let output = ice(
+        &["--check", "-"],
+        "fn f(a: int) -> int { return a; }\nf(1);\n",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(stderr(&output), "");
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn import_splices_in_another_file_once() {
+    source_file(
+        "import_lib.ic",
+        "fn sq(x: int) -> int { return x * x; }\nprint(1);\n",
+    );
+    let main = source_file(
+        "import_main.ic",
+        "import \"import_lib.ic\";\nimport \"import_lib.ic\";\nprint(sq(7));\n",
+    );
+    let output = ice(&["--eval", &main], "");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1\n49\n");
+}
+
+#[test]
+fn circular_imports_are_reported_with_the_cycle() {
+    let a = source_file("cycle_a.ic", "import \"cycle_b.ic\";\n");
+    let b = source_file("cycle_b.ic", "import \"cycle_a.ic\";\n");
+    let output = ice(&[&a], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).starts_with(&format!(
+            "import error in {}:1:8: circular import {} -> {} -> {}\n",
+            b, a, b, a
+        )),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn check_reports_type_errors_in_the_imported_file() {
+    let lib = source_file("checked_lib.ic", "let bad: bool = 1;\n");
+    let main = source_file(
+        "checked_main.ic",
+        "import \"checked_lib.ic\";\nlet good: int = 1;\n",
+    );
+    let output = ice(&["--check", &main], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).starts_with(&format!("type error in {}:1:5: ", lib)),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn check_names_the_imported_file_in_its_warnings() {
+    let lib = source_file("warned_lib.ic", "fn f(a: int) -> int { return 1; }\n");
+    let main = source_file(
+        "warned_main.ic",
+        "import \"warned_lib.ic\";\nfn g(b: int) -> int { return 1; }\n",
+    );
+    let output = ice(&["--check", &main], "");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "warning in {}: unused parameter 'a' at line 1\nwarning: unused parameter 'b' at line 2\n",
+            lib
+        )
+    );
+}
+
+#[test]
+fn help_lists_every_flag() {
+    let output = ice(&["--help"], "");
+
+    assert!(output.status.success());
+    let help = stdout(&output);
+    assert!(help.starts_with("usage:"), "{}", help);
+    for flag in [
+        "--tokens",
+        "--ast-json",
+        "--eval",
+        "--check",
+        "--help",
+        "--version",
+    ] {
+        assert!(help.contains(flag), "missing {} in {}", flag, help);
+    }
+}
+
+#[test]
+fn version_prints_the_crate_version() {
+    let output = ice(&["--version"], "");
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        format!("ice {}\n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn unknown_flag_prints_usage_and_fails() {
+    let output = ice(&["--frobnicate", "-"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("usage:"));
+}